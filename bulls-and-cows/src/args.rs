@@ -0,0 +1,64 @@
+use crate::game_utils::{GameParams, MAX_BASE};
+
+// Value of the argument that follows the given name, e.g. "--prior <file>"
+pub fn get_arg_value(args: &[String], name: &str) -> Option<String> {
+	let mut args = args.iter().skip_while(|x| *x != name);
+	args.next()?;
+	args.next().cloned()
+}
+
+// Parsed value of the argument, the default value if there is no such argument
+pub fn get_parsed_arg<T: std::str::FromStr>(
+	args: &[String],
+	name: &str,
+	default: T,
+) -> Result<T, String> {
+	match get_arg_value(args, name) {
+		Some(v) => v
+			.parse()
+			.map_err(|_| format!("Invalid value of {}: {}", name, v)),
+		None => Ok(default),
+	}
+}
+
+pub fn has_arg(args: &[String], name: &str) -> bool {
+	args.iter().any(|x| x == name)
+}
+
+// Game parameters from the arguments:
+// --len <n>, --base <n>, --alphabet <digits>, --repetitions, --max-repetitions <n>, --no-leading-zero
+pub fn get_game_params(args: &[String]) -> Result<GameParams, String> {
+	const DEFAULT_LEN: u8 = 4;
+
+	let get_u8_arg = |name: &str| -> Result<Option<u8>, String> {
+		match get_arg_value(args, name) {
+			Some(v) => match v.parse::<u8>() {
+				Ok(v) if v > 0 && v <= MAX_BASE => Ok(Some(v)),
+				_ => Err(format!("Invalid value of {}: {}", name, v)),
+			},
+			None => Ok(None),
+		}
+	};
+
+	let mut g = GameParams::new(get_u8_arg("--len")?.unwrap_or(DEFAULT_LEN));
+	if let Some(base) = get_u8_arg("--base")? {
+		g = g.with_base(base);
+	}
+	if let Some(alphabet) = get_arg_value(args, "--alphabet") {
+		g = g.with_alphabet(&alphabet.to_uppercase())?;
+	}
+	if has_arg(args, "--repetitions") {
+		g = g.with_repetitions(true);
+	}
+	if let Some(r) = get_u8_arg("--max-repetitions")? {
+		g = g.with_max_repetitions(r);
+	}
+	if has_arg(args, "--no-leading-zero") {
+		g = g.with_leading_zero(false);
+	}
+
+	if g.size() == Some(0) {
+		return Err("There are no numbers that satisfy the game parameters".to_owned());
+	}
+	Ok(g)
+}
@@ -0,0 +1,118 @@
+// Set of indexes from 0 to size, one bit per index.
+// Operations on whole sets work with 64 indexes at once
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bitset {
+	words: Vec<u64>,
+	size: usize,
+}
+
+impl Bitset {
+	pub fn new(size: usize) -> Self {
+		Self {
+			words: vec![0; size.div_ceil(64)],
+			size,
+		}
+	}
+
+	pub fn insert(&mut self, idx: usize) {
+		debug_assert!(idx < self.size);
+		self.words[idx / 64] |= 1 << (idx % 64);
+	}
+
+	pub fn remove(&mut self, idx: usize) {
+		self.words[idx / 64] &= !(1 << (idx % 64));
+	}
+
+	pub fn contains(&self, idx: usize) -> bool {
+		idx < self.size && self.words[idx / 64] & (1 << (idx % 64)) != 0
+	}
+
+	pub fn count(&self) -> usize {
+		self.words.iter().map(|x| x.count_ones() as usize).sum()
+	}
+
+	pub fn first(&self) -> Option<usize> {
+		self.iter().next()
+	}
+
+	pub fn words_mut(&mut self) -> &mut [u64] {
+		&mut self.words
+	}
+
+	// Words that contain at least one index, with their positions
+	pub fn nonzero_words(&self) -> Vec<(usize, u64)> {
+		self.words
+			.iter()
+			.enumerate()
+			.filter(|(_, x)| **x != 0)
+			.map(|(i, x)| (i, *x))
+			.collect()
+	}
+
+	// Indexes in the set, in increasing order
+	pub fn iter(&self) -> Ones<'_> {
+		Ones {
+			words: &self.words,
+			word_idx: 0,
+			cur: self.words.first().copied().unwrap_or(0),
+		}
+	}
+}
+
+impl FromIterator<usize> for Bitset {
+	// The size of the set is the maximal index plus one
+	fn from_iter<T: IntoIterator<Item = usize>>(iter: T) -> Self {
+		let v: Vec<_> = iter.into_iter().collect();
+		let mut res = Self::new(v.iter().max().map_or(0, |x| x + 1));
+		for idx in v {
+			res.insert(idx);
+		}
+		res
+	}
+}
+
+pub struct Ones<'a> {
+	words: &'a [u64],
+	word_idx: usize,
+	cur: u64,
+}
+
+impl Iterator for Ones<'_> {
+	type Item = usize;
+
+	fn next(&mut self) -> Option<usize> {
+		while self.cur == 0 {
+			self.word_idx += 1;
+			self.cur = *self.words.get(self.word_idx)?;
+		}
+		let bit = self.cur.trailing_zeros() as usize;
+		// Clear the lowest set bit
+		self.cur &= self.cur - 1;
+		Some(self.word_idx * 64 + bit)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_insert_and_iterate() {
+		let mut s = Bitset::new(200);
+		assert_eq!(s.first(), None);
+		for idx in [3, 64, 65, 127, 199, 0] {
+			s.insert(idx);
+		}
+		assert_eq!(s.count(), 6);
+		assert_eq!(s.iter().collect::<Vec<_>>(), vec![0, 3, 64, 65, 127, 199]);
+		assert!(s.contains(127));
+		assert!(!s.contains(128));
+		assert!(!s.contains(1000));
+
+		s.remove(64);
+		s.remove(0);
+		assert_eq!(s.first(), Some(3));
+		assert_eq!(s.iter().collect::<Vec<_>>(), vec![3, 65, 127, 199]);
+		assert_eq!(s, [3, 65, 127, 199].into_iter().collect());
+	}
+}
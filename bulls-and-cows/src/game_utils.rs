@@ -1,17 +1,31 @@
 use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use crate::random::Random;
 
 #[derive(Eq, PartialEq, PartialOrd, Ord, Debug, Clone, Default, Hash)]
 pub struct Number {
 	data: Vec<u8>,
 }
 
+impl Number {
+	// The number with the given digits. The digits aren't checked, see GameParams::is_valid
+	pub fn from_digits(data: Vec<u8>) -> Self {
+		Self { data }
+	}
+
+	pub fn digits(&self) -> &[u8] {
+		&self.data
+	}
+}
+
 impl Display for Number {
 	fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
 		let mut s = String::new();
 		for b in self.data.iter() {
 			match b {
-				0..=9 => s.push((b + ('0' as u8)) as char),
-				10..MAX_BASE => s.push((b - 10 + ('A' as u8)) as char),
+				0..=9 => s.push((b + b'0') as char),
+				10..MAX_BASE => s.push((b - 10 + b'A') as char),
 				_ => unreachable!(),
 			}
 		}
@@ -20,6 +34,8 @@ impl Display for Number {
 	}
 }
 
+// Parsing without a game, for numbers written in the code, e.g. in tests. It panics on a character
+// that isn't a digit or an uppercase letter. Input should be parsed by GameParams::to_number_checked
 impl<T> From<T> for Number
 where
 	T: AsRef<str>,
@@ -29,8 +45,8 @@ where
 			.as_ref()
 			.chars()
 			.map(|c| match c {
-				'0'..='9' => (c as u8) - ('0' as u8),
-				'A'..='Z' => (c as u8) - 10 - ('A' as u8),
+				'0'..='9' => (c as u8) - b'0',
+				'A'..='Z' => (c as u8) - b'A' + 10,
 				_ => panic!("Unknown character"),
 			})
 			.collect();
@@ -38,14 +54,18 @@ where
 	}
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub struct GameParams {
 	number_len: u8,
-	has_repetitions: bool,
 	base: u8,
+	// Bit i is set if the digit i is allowed. The base is the largest allowed digit plus one
+	alphabet: u64,
+	// The maximal number of occurrences of one digit in a number
+	max_repetitions: u8,
+	leading_zero: bool,
 }
 
-const MAX_BASE: u8 = 36;
+pub const MAX_BASE: u8 = 36;
 
 impl GameParams {
 	pub fn new(number_len: u8) -> Self {
@@ -54,8 +74,10 @@ impl GameParams {
 		}
 		Self {
 			number_len,
-			has_repetitions: false,
 			base: 10,
+			alphabet: (1 << 10) - 1,
+			max_repetitions: 1,
+			leading_zero: true,
 		}
 	}
 
@@ -63,8 +85,35 @@ impl GameParams {
 		self.number_len
 	}
 
+	pub fn has_repetitions(&self) -> bool {
+		self.max_repetitions() > 1
+	}
+
+	pub fn max_repetitions(&self) -> u8 {
+		u8::min(self.max_repetitions, self.number_len)
+	}
+
+	pub fn leading_zero(&self) -> bool {
+		self.leading_zero
+	}
+
+	// Allowed digits in ascending order
+	pub fn digits(&self) -> Vec<u8> {
+		(0..self.base)
+			.filter(|d| self.alphabet & (1 << d) != 0)
+			.collect()
+	}
+
 	pub fn with_repetitions(mut self, r: bool) -> Self {
-		self.has_repetitions = r;
+		self.max_repetitions = if r { MAX_BASE } else { 1 };
+		self
+	}
+
+	pub fn with_max_repetitions(mut self, max_repetitions: u8) -> Self {
+		if max_repetitions == 0 {
+			panic!("Maximal number of repetitions should be positive");
+		}
+		self.max_repetitions = max_repetitions;
 		self
 	}
 
@@ -73,223 +122,480 @@ impl GameParams {
 			panic!("Number of digits can't be more than {MAX_BASE}");
 		}
 		self.base = base;
+		self.alphabet = (1 << base) - 1;
 		self
 	}
 
-	pub fn to_number_checked(&self, s: &str) -> Option<Number> {
-		let mut res = Vec::new();
-		let mut digit_presented = vec![false; MAX_BASE as usize];
+	// Allow only the digits from the string, e.g. "123456789" or "ABCDEF"
+	pub fn with_alphabet(mut self, s: &str) -> Result<Self, String> {
+		let mut alphabet: u64 = 0;
 		for c in s.chars() {
-			match self.to_u8(c) {
-				Ok(v) => {
-					if !self.has_repetitions && digit_presented[v as usize] {
-						// It is a repetition
-						return None;
-					}
-					digit_presented[v as usize] = true;
-					res.push(v);
-				}
+			alphabet |= 1 << self.with_base(MAX_BASE).to_u8(c)?;
+		}
+		if alphabet == 0 {
+			return Err("The alphabet is empty".to_owned());
+		}
+		self.base = (u64::BITS - alphabet.leading_zeros()) as u8;
+		self.alphabet = alphabet;
+		Ok(self)
+	}
 
-				Err(_) => return None,
+	// If it is false, the first digit of a number can't be zero
+	pub fn with_leading_zero(mut self, leading_zero: bool) -> Self {
+		self.leading_zero = leading_zero;
+		self
+	}
+
+	// Check all constraints for the number: length, alphabet, repetitions and leading zero
+	pub fn is_valid(&self, num: &Number) -> bool {
+		if num.data.len() != self.number_len as usize {
+			return false;
+		}
+		if !self.leading_zero && num.data.first() == Some(&0) {
+			return false;
+		}
+		let mut digit_count = [0; MAX_BASE as usize];
+		for d in num.data.iter() {
+			if *d >= self.base || self.alphabet & (1 << d) == 0 {
+				return false;
+			}
+			digit_count[*d as usize] += 1;
+			if digit_count[*d as usize] > self.max_repetitions {
+				return false;
 			}
 		}
-		if res.len() as u8 != self.number_len {
-			None
+		true
+	}
+
+	// True if the constraints are only the length, the base and the repetitions flag
+	fn is_simple(&self) -> bool {
+		self.leading_zero
+			&& self.alphabet == (1 << self.base) - 1
+			&& (self.max_repetitions() == 1 || self.max_repetitions() == self.number_len)
+	}
+
+	// Bulls and cows together can't be more than the length of numbers.
+	// Responses usually come from the user, so the sum is checked for overflow
+	pub fn is_valid_response(&self, bulls: u8, cows: u8) -> bool {
+		bulls
+			.checked_add(cows)
+			.is_some_and(|x| x <= self.number_len)
+	}
+
+	// True if there are no constraints but the length and the base, and digits don't repeat.
+	// Then any number turns into any other by renaming the digits and all first guesses are equally good
+	pub fn is_unconstrained(&self) -> bool {
+		self.is_simple() && self.max_repetitions() == 1
+	}
+
+	// Number of indexes used by rank and unrank. Indexes enumerate numbers over the allowed digits
+	// in the order of get_numbers_iter, before filtering by the leading zero and the repetitions limit.
+	// So it is equal to size() for games without these constraints. None means the value doesn't fit into u64
+	pub fn index_space_size(&self) -> Option<u64> {
+		let k = self.digits().len() as u64;
+		let n = self.number_len as u64;
+		if !self.has_repetitions() {
+			if k < n {
+				return Some(0);
+			}
+			binomial(k, n)?.checked_mul(factorial(n)?)
 		} else {
-			Some(Number { data: res })
+			k.checked_pow(n as u32)
 		}
 	}
 
-	fn to_string_checked(&self, num: &Number) -> Option<String> {
-		let mut res = String::new();
-		let mut digit_presented = vec![false; MAX_BASE as usize];
+	// Exact count of numbers that satisfy all constraints. None means the value doesn't fit into u64
+	pub fn size(&self) -> Option<u64> {
+		let digits = self.digits();
+		let r = self.max_repetitions();
+		let limits = vec![r; digits.len()];
+		let total = count_sequences(self.number_len, &limits)?;
+		if self.leading_zero || digits.first() != Some(&0) || self.number_len == 0 {
+			return u64::try_from(total).ok();
+		}
+		// Subtract numbers that start with zero: the rest of such a number has one zero less
+		let mut limits = limits;
+		limits[0] -= 1;
+		let with_zero = count_sequences(self.number_len - 1, &limits)?;
+		u64::try_from(total - with_zero).ok()
+	}
 
-		if num.data.len() as u8 != self.number_len {
+	// Index of the number, see index_space_size. None if the number doesn't satisfy the constraints
+	pub fn rank(&self, num: &Number) -> Option<u64> {
+		if !self.is_valid(num) {
 			return None;
 		}
-		for b in num.data.iter() {
-			if !self.has_repetitions && digit_presented[*b as usize] {
-				return None;
+		let digits = self.digits();
+		let mut digit_idx = [0; MAX_BASE as usize];
+		for (i, d) in digits.iter().enumerate() {
+			digit_idx[*d as usize] = i as u8;
+		}
+		let idx: Vec<_> = num.data.iter().map(|d| digit_idx[*d as usize]).collect();
+		if self.has_repetitions() {
+			rank_with_repetitions(&idx, digits.len() as u8)
+		} else {
+			rank_without_repetitions(&idx, digits.len() as u8)
+		}
+	}
+
+	// The number with the given index, see index_space_size.
+	// None if the index is out of range or the number doesn't satisfy the constraints
+	pub fn unrank(&self, idx: u64) -> Option<Number> {
+		if idx >= self.index_space_size()? {
+			return None;
+		}
+		let digits = self.digits();
+		let k = digits.len() as u8;
+		let data = if self.has_repetitions() {
+			unrank_with_repetitions(idx, self.number_len, k)
+		} else {
+			unrank_without_repetitions(idx, self.number_len, k)?
+		};
+		let num = Number {
+			data: data.into_iter().map(|i| digits[i as usize]).collect(),
+		};
+		if self.is_valid(&num) {
+			Some(num)
+		} else {
+			None
+		}
+	}
+
+	// Random number that satisfies all constraints, all numbers are equally likely.
+	// None if there are no such numbers
+	pub fn random_number(&self, random: &mut Random) -> Option<Number> {
+		if self.size() == Some(0) {
+			return None;
+		}
+		// Random indexes of numbers that don't satisfy the constraints are rejected
+		if let Some(size) = self.index_space_size() {
+			loop {
+				if let Some(num) = self.unrank(random.gen_range(size)) {
+					return Some(num);
+				}
+			}
+		}
+		let digits = self.digits();
+		let n = self.number_len as usize;
+		// Numbers that don't satisfy the rest constraints are rejected
+		loop {
+			let data = if self.has_repetitions() {
+				(0..n)
+					.map(|_| digits[random.gen_range(digits.len() as u64) as usize])
+					.collect()
+			} else {
+				let mut data = digits.clone();
+				random.shuffle(&mut data);
+				data.truncate(n);
+				data
+			};
+			let num = Number { data };
+			if self.is_valid(&num) {
+				return Some(num);
 			}
-			digit_presented[*b as usize] = true;
-			match self.to_char(*b) {
-				Ok(c) => res.push(c),
+		}
+	}
+
+	// The conversions take &self like other methods of GameParams, although it's Copy
+	#[allow(clippy::wrong_self_convention)]
+	pub fn to_number_checked(&self, s: &str) -> Option<Number> {
+		let mut res = Vec::new();
+		for c in s.chars() {
+			match self.to_u8(c) {
+				Ok(v) => res.push(v),
 				Err(_) => return None,
 			}
 		}
+		let res = Number { data: res };
+		if self.is_valid(&res) {
+			Some(res)
+		} else {
+			None
+		}
+	}
 
-		Some(res)
+	#[cfg(test)]
+	#[allow(clippy::wrong_self_convention)]
+	fn to_string_checked(&self, num: &Number) -> Option<String> {
+		if !self.is_valid(num) {
+			return None;
+		}
+		num.data.iter().map(|b| self.to_char(*b).ok()).collect()
 	}
 
+	#[allow(clippy::wrong_self_convention)]
 	fn to_char(&self, b: u8) -> Result<char, String> {
 		if b >= self.base {
 			return Err(format!("value {b} is too large"));
 		}
+		if self.alphabet & (1 << b) == 0 {
+			return Err(format!("value {b} isn't in the alphabet"));
+		}
 		match b {
-			0..=9 => Ok((b + ('0' as u8)) as char),
-			10..=MAX_BASE => Ok((b - 10 + ('A' as u8)) as char),
+			0..=9 => Ok((b + b'0') as char),
+			10..=MAX_BASE => Ok((b - 10 + b'A') as char),
 			_ => unreachable!(),
 		}
 	}
 
+	#[allow(clippy::wrong_self_convention)]
 	fn to_u8(&self, c: char) -> Result<u8, String> {
 		let v = match c {
-			'0'..='9' => ((c as u8) - ('0' as u8)) as u8,
-			'A'..='Z' => ((c as u8) - ('A' as u8) + 10) as u8,
+			'0'..='9' => (c as u8) - b'0',
+			'A'..='Z' => (c as u8) - b'A' + 10,
 			_ => return Err(format!("Char {c} isn't in valid range")),
 		};
 		if v >= self.base {
 			Err(format!("Char {c} represents too large digit"))
+		} else if self.alphabet & (1 << v) == 0 {
+			Err(format!("Char {c} isn't in the alphabet"))
 		} else {
 			Ok(v)
 		}
 	}
 
 	pub fn calc_bc(&self, a: &Number, b: &Number) -> (u8, u8) {
-		calc_bc_with_base(a, b, self.base)
-	}
-}
-
-fn get_numbers_iter_ref(g: &GameParams) -> Box<dyn RefIter<Item = Number>> {
-	if !g.has_repetitions {
-		Box::new(NumbersWithoutRepetitions {
-			cur_number: None,
-			used_digits: Vec::new(),
-			number_len: g.number_len,
-			total_digits_number: g.base,
-		})
-	} else {
-		Box::new(NumbersWithRepetitions {
-			cur_number: None,
-			number_len: g.number_len,
-			total_digits_number: g.base,
-		})
+		debug_assert!(a.data.iter().all(|d| *d < self.base));
+		calc_bc_digits(a, b)
 	}
 }
 
-pub fn get_numbers_iter(g: &GameParams) -> Box<dyn Iterator<Item = Number>> {
-	if !g.has_repetitions {
-		Box::new(NumbersWithoutRepetitions {
-			cur_number: None,
-			used_digits: Vec::new(),
-			number_len: g.number_len,
-			total_digits_number: g.base,
-		})
-	} else {
-		Box::new(NumbersWithRepetitions {
-			cur_number: None,
-			number_len: g.number_len,
-			total_digits_number: g.base,
-		})
+// Short description of the game which can be used as a part of file names, e.g. "4_0123456789_r1"
+impl Display for GameParams {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+		let alphabet: String = self
+			.digits()
+			.into_iter()
+			.map(|d| self.to_char(d).unwrap())
+			.collect();
+		write!(
+			f,
+			"{}_{}_r{}",
+			self.number_len,
+			alphabet,
+			self.max_repetitions()
+		)?;
+		if !self.leading_zero {
+			f.write_str("_nlz")?;
+		}
+		Ok(())
 	}
 }
 
+// Lending iterator: the item is borrowed from the iterator, so items aren't allocated
 pub trait RefIter {
 	type Item;
 
 	fn next(&mut self) -> Option<&Self::Item>;
 }
 
-struct NumbersWithoutRepetitions {
-	cur_number: Option<Number>,
-	used_digits: Vec<bool>,
-	number_len: u8,
-	total_digits_number: u8,
+// Numbers that satisfy the game constraints in the order of their indexes, see GameParams::rank.
+// Numbers are generated over the indexes of allowed digits, then indexes are replaced by digits
+// and the numbers that violate the rest of constraints are skipped
+pub struct NumbersRefIter {
+	// None if there are no numbers in the range
+	raw: Option<Box<dyn RefIter<Item = Number> + Send>>,
+	digits: Vec<u8>,
+	game: GameParams,
+	check_constraints: bool,
+	cur: Number,
+	// Index of the next number generated by raw
+	index: u64,
+	end: u64,
 }
 
-impl Iterator for NumbersWithoutRepetitions {
-	type Item = Number;
-
-	fn next(&mut self) -> Option<Self::Item> {
-		let res = (self as &mut dyn RefIter<Item = Self::Item>).next();
-		res.map(|x| x.clone())
+impl NumbersRefIter {
+	// Index of the number returned by the last call of next
+	pub fn index(&self) -> u64 {
+		self.index - 1
 	}
 }
 
-impl RefIter for NumbersWithoutRepetitions {
+impl RefIter for NumbersRefIter {
 	type Item = Number;
 
-	fn next(&mut self) -> Option<&Self::Item> {
-		match &mut self.cur_number {
-			Some(num) => {
-				if !next_permutation(&mut num.data) {
-					if next_permutation(&mut self.used_digits) {
-						num.data = self
-							.used_digits
-							.iter()
-							.enumerate()
-							.filter_map(|(i, v)| if *v { Some(i as u8) } else { None })
-							.collect();
-					} else {
-						self.cur_number = None;
-					}
-				}
-			}
-
-			None => {
-				self.used_digits = std::iter::repeat(false)
-					.take((self.total_digits_number - self.number_len) as usize)
-					.chain(std::iter::repeat(true).take(self.number_len as usize))
-					.collect();
-
-				debug_assert_eq!(self.used_digits.len() as u8, self.total_digits_number);
-				self.cur_number = Some(Number {
-					data: self
-						.used_digits
-						.iter()
-						.enumerate()
-						.filter_map(|(i, v)| if *v { Some(i as u8) } else { None })
-						.collect(),
-				});
+	fn next(&mut self) -> Option<&Number> {
+		while self.index < self.end {
+			let raw = self.raw.as_mut()?.next()?;
+			self.index += 1;
+			self.cur.data.clear();
+			self.cur
+				.data
+				.extend(raw.data.iter().map(|d| self.digits[*d as usize]));
+			if !self.check_constraints || self.game.is_valid(&self.cur) {
+				return Some(&self.cur);
 			}
 		}
+		None
+	}
+}
 
-		match &self.cur_number {
-			Some(x) => Some(x),
-			None => None,
-		}
+// Numbers with indexes from the range, see NumbersRefIter.
+// If the index space doesn't fit into u64, only its beginning can be iterated
+pub fn get_numbers_iter_range(g: &GameParams, range: std::ops::Range<u64>) -> NumbersRefIter {
+	let digits = g.digits();
+	let k = digits.len() as u8;
+	let end = u64::min(range.end, g.index_space_size().unwrap_or(u64::MAX));
+	let start = u64::min(range.start, end);
+	let raw: Option<Box<dyn RefIter<Item = Number> + Send>> = if start == end {
+		None
+	} else if g.has_repetitions() {
+		Some(Box::new(NumbersWithRepetitions::new(
+			g.number_len,
+			k,
+			start,
+		)))
+	} else {
+		NumbersWithoutRepetitions::new(g.number_len, k, start)
+			.map(|x| Box::new(x) as Box<dyn RefIter<Item = Number> + Send>)
+	};
+	NumbersRefIter {
+		raw,
+		digits,
+		game: *g,
+		check_constraints: !g.is_simple(),
+		cur: Number::default(),
+		index: start,
+		end,
 	}
 }
 
-struct NumbersWithRepetitions {
-	cur_number: Option<Number>,
-	number_len: u8,
-	total_digits_number: u8,
+// All numbers that satisfy the game constraints, see NumbersRefIter
+pub fn get_numbers_iter_ref(g: &GameParams) -> NumbersRefIter {
+	get_numbers_iter_range(g, 0..u64::MAX)
 }
 
-impl Iterator for NumbersWithRepetitions {
-	type Item = Number;
+// Same as get_numbers_iter_ref, but every number is cloned
+pub fn get_numbers_iter(g: &GameParams) -> Box<dyn Iterator<Item = Number>> {
+	let mut it = get_numbers_iter_ref(g);
+	Box::new(std::iter::from_fn(move || it.next().cloned()))
+}
+
+// Call f for all numbers in parallel threads, one thread for every state.
+// The index space is split into chunks, every thread takes the next unprocessed chunk
+// and calls f with its own state for the numbers of the chunk. The first error stops all threads
+pub fn par_for_each_number<T: Send, E: Send>(
+	g: &GameParams,
+	mut states: Vec<T>,
+	f: impl Fn(&mut T, &Number) -> Result<(), E> + Sync,
+) -> Result<Vec<T>, E> {
+	let size = g.index_space_size().unwrap_or(u64::MAX);
+	// Many chunks balance the load between threads, big chunks have less overhead
+	let chunk_size = (size / (states.len() as u64 * 16).max(1)).clamp(1, 1 << 16);
+	let next_chunk = AtomicU64::new(0);
+	let stop = AtomicBool::new(false);
+	let (next_chunk, stop, f) = (&next_chunk, &stop, &f);
 
-	fn next(&mut self) -> Option<Self::Item> {
-		let res = (self as &mut dyn RefIter<Item = Self::Item>).next();
-		res.map(|x| x.clone())
+	let mut errors = std::thread::scope(|s| {
+		let handles: Vec<_> = states
+			.iter_mut()
+			.map(|state| {
+				s.spawn(move || {
+					while !stop.load(Ordering::Relaxed) {
+						let start = next_chunk.fetch_add(chunk_size, Ordering::Relaxed);
+						if start >= size {
+							break;
+						}
+						let mut it =
+							get_numbers_iter_range(g, start..start.saturating_add(chunk_size));
+						while let Some(x) = it.next() {
+							if let Err(err) = f(state, x) {
+								stop.store(true, Ordering::Relaxed);
+								return Err(err);
+							}
+						}
+					}
+					Ok(())
+				})
+			})
+			.collect();
+		handles
+			.into_iter()
+			.filter_map(|h| h.join().unwrap().err())
+			.collect::<Vec<_>>()
+	});
+	match errors.pop() {
+		Some(err) => Err(err),
+		None => Ok(states),
 	}
 }
 
-impl RefIter for NumbersWithRepetitions {
+// Arrangements of digit indexes without repetitions in the lexicographical order.
+// The first number_len elements of perm are the number, the rest are the unused digits in the increasing order
+struct NumbersWithoutRepetitions {
+	perm: Vec<u8>,
+	cur_number: Number,
+	// The current number isn't returned yet
+	pending: bool,
+}
+
+impl NumbersWithoutRepetitions {
+	// Start from the number with the given index. None if there is no such number
+	fn new(number_len: u8, total_digits_number: u8, start: u64) -> Option<Self> {
+		let data = unrank_without_repetitions(start, number_len, total_digits_number)?;
+		let perm = data
+			.iter()
+			.copied()
+			.chain((0..total_digits_number).filter(|d| !data.contains(d)))
+			.collect();
+		Some(Self {
+			perm,
+			cur_number: Number { data },
+			pending: true,
+		})
+	}
+}
+
+impl RefIter for NumbersWithoutRepetitions {
 	type Item = Number;
 
 	fn next(&mut self) -> Option<&Self::Item> {
-		match &mut self.cur_number {
-			Some(num) => {
-				if !increase_vector(&mut num.data, self.total_digits_number) {
-					self.cur_number = None;
-				}
+		if self.pending {
+			self.pending = false;
+		} else {
+			// The unused digits in the decreasing order make the last permutation with this prefix,
+			// so the next permutation is the next number
+			let len = self.cur_number.data.len();
+			self.perm[len..].reverse();
+			if !next_permutation(&mut self.perm) {
+				return None;
 			}
+			self.cur_number.data.copy_from_slice(&self.perm[..len]);
+		}
+		Some(&self.cur_number)
+	}
+}
 
-			None => {
-				let v = std::iter::repeat(0)
-					.take(self.number_len as usize)
-					.collect();
+struct NumbersWithRepetitions {
+	cur_number: Number,
+	total_digits_number: u8,
+	// The current number isn't returned yet
+	pending: bool,
+}
 
-				self.cur_number = Some(Number { data: v });
-			}
+impl NumbersWithRepetitions {
+	// Start from the number with the given index
+	fn new(number_len: u8, total_digits_number: u8, start: u64) -> Self {
+		Self {
+			cur_number: Number {
+				data: unrank_with_repetitions(start, number_len, total_digits_number),
+			},
+			total_digits_number,
+			pending: true,
 		}
+	}
+}
 
-		match &self.cur_number {
-			Some(x) => Some(x),
-			None => None,
+impl RefIter for NumbersWithRepetitions {
+	type Item = Number;
+
+	fn next(&mut self) -> Option<&Self::Item> {
+		if self.pending {
+			self.pending = false;
+		} else if !increase_vector(&mut self.cur_number.data, self.total_digits_number - 1) {
+			return None;
 		}
+		Some(&self.cur_number)
 	}
 }
 
@@ -310,7 +616,7 @@ fn next_permutation<T: Ord>(a: &mut [T]) -> bool {
 	}
 	a.swap(i, j);
 	a[i + 1..].reverse();
-	return true;
+	true
 }
 
 fn increase_vector(a: &mut [u8], maxval: u8) -> bool {
@@ -327,25 +633,141 @@ fn increase_vector(a: &mut [u8], maxval: u8) -> bool {
 			break;
 		}
 	}
-	return true;
+	true
+}
+
+fn binomial(n: u64, k: u64) -> Option<u64> {
+	if k > n {
+		return Some(0);
+	}
+	let k = u64::min(k, n - k);
+	let mut res: u64 = 1;
+	for i in 0..k {
+		// res * (n - i) is divisible by (i + 1), because it is C(n, i + 1) * (i + 1)
+		res = res.checked_mul(n - i)? / (i + 1);
+	}
+	Some(res)
+}
+
+fn factorial(n: u64) -> Option<u64> {
+	(1..=n).try_fold(1u64, |acc, x| acc.checked_mul(x))
+}
+
+// Number of sequences of the given length where the digit i is used at most limits[i] times
+fn count_sequences(len: u8, limits: &[u8]) -> Option<u128> {
+	let len = len as usize;
+	let binomials: Vec<Vec<u128>> = (0..=len)
+		.map(|n| {
+			(0..=n)
+				.map(|k| binomial(n as u64, k as u64).unwrap() as u128)
+				.collect()
+		})
+		.collect();
+
+	// f[m] is the number of sequences of length m over the digits processed so far
+	let mut f = vec![0u128; len + 1];
+	f[0] = 1;
+	for limit in limits {
+		let mut new_f = vec![0u128; len + 1];
+		for (m, new_val) in new_f.iter_mut().enumerate() {
+			// c is the count of the current digit, it takes c positions among m
+			for c in 0..=usize::min(*limit as usize, m) {
+				let v = f[m - c].checked_mul(binomials[m][c])?;
+				*new_val = new_val.checked_add(v)?;
+			}
+		}
+		f = new_f;
+	}
+	Some(f[len])
+}
+
+// Rank of digits in the order of NumbersWithRepetitions, i.e. the value of the number in base k
+fn rank_with_repetitions(digits: &[u8], k: u8) -> Option<u64> {
+	digits.iter().try_fold(0u64, |acc, d| {
+		acc.checked_mul(k as u64)?.checked_add(*d as u64)
+	})
+}
+
+fn unrank_with_repetitions(mut idx: u64, len: u8, k: u8) -> Vec<u8> {
+	let mut res = vec![0; len as usize];
+	for d in res.iter_mut().rev() {
+		*d = (idx % k as u64) as u8;
+		idx /= k as u64;
+	}
+	res
+}
+
+// Number of arrangements of r elements out of n
+fn arrangements(n: u64, r: u64) -> Option<u64> {
+	(n - r + 1..=n).try_fold(1u64, |acc, x| acc.checked_mul(x))
+}
+
+// Rank of digits in the order of NumbersWithoutRepetitions, i.e. in the lexicographical order.
+// Every unused digit that is less than the digit at a position starts a block of arrangements of the rest digits
+fn rank_without_repetitions(digits: &[u8], k: u8) -> Option<u64> {
+	let (len, k) = (digits.len() as u64, k as u64);
+	let mut used = vec![false; k as usize];
+	let mut res: u64 = 0;
+	for (i, d) in digits.iter().enumerate() {
+		let less_unused = used[..*d as usize].iter().filter(|x| !**x).count() as u64;
+		let block = arrangements(k - i as u64 - 1, len - i as u64 - 1)?;
+		res = res.checked_add(less_unused.checked_mul(block)?)?;
+		used[*d as usize] = true;
+	}
+	Some(res)
+}
+
+fn unrank_without_repetitions(mut idx: u64, len: u8, k: u8) -> Option<Vec<u8>> {
+	if k < len {
+		return None;
+	}
+	let (len, k) = (len as u64, k as u64);
+	let mut unused: Vec<u8> = (0..k as u8).collect();
+	let mut res = Vec::new();
+	for i in 0..len {
+		let block = arrangements(k - i - 1, len - i - 1)?;
+		let j = (idx / block) as usize;
+		if j >= unused.len() {
+			return None;
+		}
+		res.push(unused.remove(j));
+		idx %= block;
+	}
+	Some(res)
 }
 
-pub fn calc_bc_with_base(a: &Number, b: &Number, base: u8) -> (u8, u8) {
-	let mut count_a = [0; MAX_BASE as usize];
-	let mut count_b = [0; MAX_BASE as usize];
-	let mut bulls: i32 = 0;
+// FNV-1a hash of all lines. Unlike std hashers, it is stable, so it can be saved to files
+pub fn calc_checksum(lines: &[String]) -> u64 {
+	let mut h: u64 = 0xcbf29ce484222325;
+	for line in lines {
+		for b in line.bytes().chain(std::iter::once(b'\n')) {
+			h ^= b as u64;
+			h = h.wrapping_mul(0x100000001b3);
+		}
+	}
+	h
+}
+
+// Bulls and cows of two numbers of the same length, the digits can be of any base up to MAX_BASE
+pub fn calc_bc_digits(a: &Number, b: &Number) -> (u8, u8) {
+	// Digits of b that aren't matched with digits of a yet
+	let mut count_b = [0u8; MAX_BASE as usize];
+	let mut bulls = 0;
 	for (digit_a, digit_b) in a.data.iter().zip(b.data.iter()) {
 		if *digit_a == *digit_b {
 			bulls += 1;
 		}
-		count_a[*digit_a as usize] += 1;
 		count_b[*digit_b as usize] += 1;
 	}
-	let mut cows: i32 = -bulls;
-	for (c_a, c_b) in count_a.iter().take(base as usize).zip(count_b.iter()) {
-		cows += i32::min(*c_a, *c_b);
+	let mut common = 0;
+	for digit_a in a.data.iter() {
+		let c = &mut count_b[*digit_a as usize];
+		if *c > 0 {
+			*c -= 1;
+			common += 1;
+		}
 	}
-	(bulls as u8, cows as u8)
+	(bulls, common - bulls)
 }
 
 #[cfg(test)]
@@ -509,9 +931,7 @@ mod test {
 	}
 
 	fn gen_number(b: &[u8]) -> Number {
-		Number {
-			data: b.iter().map(|x| *x).collect(),
-		}
+		Number { data: b.to_vec() }
 	}
 
 	#[test]
@@ -540,31 +960,393 @@ mod test {
 	#[test]
 	fn test_calc_bc() {
 		assert_eq!(
-			calc_bc_with_base(&Number::from("0123"), &Number::from("0432"), 5),
+			calc_bc_digits(&Number::from("0123"), &Number::from("0432")),
 			(1, 2)
 		);
 		assert_eq!(
-			calc_bc_with_base(&Number::from("0312"), &Number::from("0123"), 5),
+			calc_bc_digits(&Number::from("0312"), &Number::from("0123")),
 			(1, 3)
 		);
 		assert_eq!(
-			calc_bc_with_base(&Number::from("1234"), &Number::from("5678"), 10),
+			calc_bc_digits(&Number::from("1234"), &Number::from("5678")),
 			(0, 0)
 		);
 		assert_eq!(
-			calc_bc_with_base(&Number::from("12304"), &Number::from("43210"), 10),
+			calc_bc_digits(&Number::from("12304"), &Number::from("43210")),
 			(0, 5)
 		);
 		assert_eq!(
-			calc_bc_with_base(&Number::from("123456"), &Number::from("123456"), 10),
+			calc_bc_digits(&Number::from("123456"), &Number::from("123456")),
 			(6, 0)
 		);
 		assert_eq!(
-			calc_bc_with_base(&Number::from("1234"), &Number::from("7893"), 10),
+			calc_bc_digits(&Number::from("1234"), &Number::from("7893")),
 			(0, 1)
 		);
 	}
 
+	// Reference implementation: bulls are matched first, then every digit of a
+	// is matched with the first unmatched equal digit of b
+	fn naive_calc_bc(a: &Number, b: &Number) -> (u8, u8) {
+		let n = a.data.len();
+		let mut used_a = vec![false; n];
+		let mut used_b = vec![false; n];
+		let mut bulls = 0;
+		for i in 0..n {
+			if a.data[i] == b.data[i] {
+				used_a[i] = true;
+				used_b[i] = true;
+				bulls += 1;
+			}
+		}
+		let mut cows = 0;
+		for i in (0..n).filter(|i| !used_a[*i]) {
+			if let Some(j) = (0..n).find(|j| !used_b[*j] && a.data[i] == b.data[*j]) {
+				used_b[j] = true;
+				cows += 1;
+			}
+		}
+		(bulls, cows)
+	}
+
+	#[test]
+	fn test_calc_bc_exhaustive() {
+		for base in 1..=6 {
+			for len in 1..=4 {
+				for repetitions in [false, true] {
+					let g = GameParams::new(len)
+						.with_base(base)
+						.with_repetitions(repetitions);
+					let numbers: Vec<_> = get_numbers_iter(&g).collect();
+					for a in numbers.iter() {
+						for b in numbers.iter() {
+							assert_eq!(
+								calc_bc_digits(a, b),
+								naive_calc_bc(a, b),
+								"{} {} in {}",
+								a,
+								b,
+								g
+							);
+						}
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_calc_bc_random() {
+		let mut random = Random::new(7);
+		for _ in 0..20000 {
+			let base = 1 + random.gen_range(MAX_BASE as u64) as u8;
+			let len = 1 + random.gen_range(12) as usize;
+			// Small bases give many repeated digits
+			let mut gen = || {
+				Number::from_digits(
+					(0..len)
+						.map(|_| random.gen_range(base as u64) as u8)
+						.collect(),
+				)
+			};
+			let (a, b) = (gen(), gen());
+			let (bulls, cows) = calc_bc_digits(&a, &b);
+			assert_eq!((bulls, cows), naive_calc_bc(&a, &b), "{} {}", a, b);
+			// The response doesn't depend on the order of the arguments
+			assert_eq!(calc_bc_digits(&b, &a), (bulls, cows));
+			assert!(bulls + cows <= len as u8);
+		}
+	}
+
+	#[test]
+	fn test_round_trip() {
+		let games = [
+			GameParams::new(2).with_base(MAX_BASE),
+			GameParams::new(3).with_base(17).with_repetitions(true),
+			GameParams::new(3)
+				.with_alphabet("0AZ9")
+				.unwrap()
+				.with_max_repetitions(2),
+			GameParams::new(4).with_leading_zero(false),
+		];
+		for g in games {
+			for x in get_numbers_iter(&g) {
+				let s = g.to_string_checked(&x).unwrap();
+				assert_eq!(s, x.to_string());
+				assert_eq!(g.to_number_checked(&s), Some(x.clone()));
+				assert_eq!(Number::from(&s), x);
+			}
+		}
+		assert_eq!(Number::from("09AZ").digits(), [0, 9, 10, 35]);
+	}
+
+	// Parsing of random strings of digits, letters and other characters, the seed is fixed.
+	// Parsing never panics, and every accepted string is printed back the same way
+	// This isn't fuzzing, coverage-guided fuzzing of to_number_checked and calc_bc is out of scope:
+	// cargo fuzz targets link against a library, and this crate is only a binary
+	#[test]
+	fn test_random_parsing() {
+		const CHARS: [char; 12] = ['0', '1', '7', '9', 'A', 'F', 'Z', 'a', ' ', '-', 'й', '\0'];
+		let games = [
+			GameParams::new(3),
+			GameParams::new(2).with_base(16).with_repetitions(true),
+			GameParams::new(3)
+				.with_alphabet("17AZ")
+				.unwrap()
+				.with_leading_zero(false),
+		];
+		let mut random = Random::new(3);
+		for _ in 0..20000 {
+			let len = random.gen_range(5) as usize;
+			let s: String = (0..len)
+				.map(|_| CHARS[random.gen_range(CHARS.len() as u64) as usize])
+				.collect();
+			for g in games.iter() {
+				if let Some(x) = g.to_number_checked(&s) {
+					assert!(g.is_valid(&x));
+					assert_eq!(x.to_string(), s);
+					assert_eq!(g.to_string_checked(&x).as_deref(), Some(s.as_str()));
+					assert!(g.rank(&x).is_some());
+				}
+			}
+		}
+	}
+
+	#[test]
+	#[should_panic(expected = "Unknown character")]
+	fn test_from_invalid_str() {
+		let _ = Number::from("12a");
+	}
+
+	fn gen_strings(g: &GameParams) -> Vec<String> {
+		let mut v: Vec<_> = get_numbers_iter(g).map(|x| x.to_string()).collect();
+		v.sort();
+		v
+	}
+
+	#[test]
+	fn test_gen_numbers_with_repetitions() {
+		let g = GameParams::new(2).with_base(3).with_repetitions(true);
+		assert_eq!(
+			gen_strings(&g),
+			["00", "01", "02", "10", "11", "12", "20", "21", "22"]
+		);
+
+		let g = GameParams::new(3).with_base(2).with_max_repetitions(2);
+		assert_eq!(gen_strings(&g), ["001", "010", "011", "100", "101", "110"]);
+	}
+
+	#[test]
+	fn test_gen_numbers_with_constraints() {
+		let g = GameParams::new(2).with_base(3).with_leading_zero(false);
+		assert_eq!(gen_strings(&g), ["10", "12", "20", "21"]);
+
+		let g = GameParams::new(2).with_alphabet("19").unwrap();
+		assert_eq!(gen_strings(&g), ["19", "91"]);
+
+		let g = GameParams::new(2)
+			.with_alphabet("AC")
+			.unwrap()
+			.with_repetitions(true);
+		assert_eq!(gen_strings(&g), ["AA", "AC", "CA", "CC"]);
+
+		let g = GameParams::new(3).with_alphabet("0123").unwrap();
+		assert_eq!(gen_strings(&g).len(), 24);
+
+		let g = GameParams::new(4).with_base(3);
+		assert!(gen_strings(&g).is_empty());
+
+		assert!(GameParams::new(2).with_alphabet("").is_err());
+		assert!(GameParams::new(2).with_alphabet("1a").is_err());
+	}
+
+	#[test]
+	fn test_to_number_with_constraints() {
+		let g = GameParams::new(4)
+			.with_alphabet("123456789")
+			.unwrap()
+			.with_max_repetitions(2);
+		assert!(g.to_number_checked("1123").is_some());
+		assert!(g.to_number_checked("1113").is_none());
+		assert!(g.to_number_checked("1023").is_none());
+		assert!(g.to_number_checked("12345").is_none());
+
+		let g = GameParams::new(4).with_leading_zero(false);
+		assert!(g.to_number_checked("1023").is_some());
+		assert!(g.to_number_checked("0123").is_none());
+		assert_eq!(g.to_string_checked(&gen_number(&[0, 1, 2, 3])), None);
+		assert_eq!(
+			g.to_string_checked(&gen_number(&[3, 1, 2, 0])).as_deref(),
+			Some("3120")
+		);
+
+		let g = GameParams::new(3).with_alphabet("ABF").unwrap();
+		assert!(g.to_number_checked("FAB").is_some());
+		assert!(g.to_number_checked("FAC").is_none());
+		assert_eq!(g.to_string(), "3_ABF_r1");
+		assert_eq!(
+			GameParams::new(4).with_leading_zero(false).to_string(),
+			"4_0123456789_r1_nlz"
+		);
+	}
+
+	#[test]
+	fn test_rank_and_unrank() {
+		let games = [
+			GameParams::new(1).with_base(5),
+			GameParams::new(3).with_base(5),
+			GameParams::new(4),
+			GameParams::new(5).with_base(5),
+			GameParams::new(3).with_base(4).with_repetitions(true),
+			GameParams::new(3).with_base(4).with_max_repetitions(2),
+			GameParams::new(3).with_leading_zero(false),
+			GameParams::new(3)
+				.with_alphabet("02468")
+				.unwrap()
+				.with_repetitions(true)
+				.with_leading_zero(false),
+			GameParams::new(3).with_alphabet("1AZ").unwrap(),
+		];
+		for g in games {
+			let numbers: Vec<_> = get_numbers_iter(&g).collect();
+			assert_eq!(g.size(), Some(numbers.len() as u64), "{}", g);
+
+			let mut prev_rank = None;
+			for x in numbers.iter() {
+				let r = g.rank(x).unwrap();
+				assert!(prev_rank < Some(r), "{} {}", g, x);
+				assert!(r < g.index_space_size().unwrap());
+				assert_eq!(g.unrank(r).as_ref(), Some(x));
+				prev_rank = Some(r);
+			}
+			let valid_indexes = (0..g.index_space_size().unwrap())
+				.filter_map(|i| g.unrank(i))
+				.count();
+			assert_eq!(valid_indexes, numbers.len());
+		}
+
+		let g = GameParams::new(4);
+		assert_eq!(g.rank(&Number::from("0123")), Some(0));
+		assert_eq!(g.rank(&Number::from("0124")), Some(1));
+		assert_eq!(g.unrank(5039), Some(Number::from("9876")));
+		assert_eq!(g.rank(&Number::from("1123")), None);
+		assert_eq!(g.unrank(5040), None);
+	}
+
+	#[test]
+	fn test_iter_range() {
+		let games = [
+			GameParams::new(3).with_base(6),
+			GameParams::new(3).with_base(4).with_repetitions(true),
+			GameParams::new(3)
+				.with_alphabet("13579")
+				.unwrap()
+				.with_max_repetitions(2)
+				.with_leading_zero(false),
+		];
+		for g in games {
+			let all: Vec<_> = get_numbers_iter(&g).collect();
+			let mut chunks = Vec::new();
+			for start in (0..g.index_space_size().unwrap()).step_by(7) {
+				let mut it = get_numbers_iter_range(&g, start..start + 7);
+				while let Some(x) = it.next() {
+					let x = x.clone();
+					assert_eq!(g.rank(&x), Some(it.index()));
+					chunks.push(x);
+				}
+			}
+			assert_eq!(chunks, all, "{}", g);
+		}
+
+		let g = GameParams::new(8).with_base(16);
+		let mut it = get_numbers_iter_range(&g, 1000..u64::MAX);
+		assert_eq!(it.next(), g.unrank(1000).as_ref());
+		assert_eq!(it.next(), g.unrank(1001).as_ref());
+		assert!(
+			get_numbers_iter_range(&GameParams::new(4).with_base(3), 0..10)
+				.next()
+				.is_none()
+		);
+	}
+
+	#[test]
+	fn test_par_for_each_number() {
+		let g = GameParams::new(4).with_max_repetitions(2);
+		let counts = par_for_each_number(&g, vec![0; 3], |count, _| -> Result<(), ()> {
+			*count += 1;
+			Ok(())
+		});
+		assert_eq!(counts.unwrap().iter().sum::<u64>(), g.size().unwrap());
+
+		let res = par_for_each_number(&g, vec![(); 2], |_, x| {
+			if x.to_string() == "1123" {
+				Err(x.clone())
+			} else {
+				Ok(())
+			}
+		});
+		assert_eq!(res, Err(Number::from("1123")));
+	}
+
+	#[test]
+	fn test_size() {
+		assert_eq!(GameParams::new(4).size(), Some(5040));
+		assert_eq!(
+			GameParams::new(4).with_leading_zero(false).size(),
+			Some(4536)
+		);
+		assert_eq!(
+			GameParams::new(4).with_repetitions(true).size(),
+			Some(10000)
+		);
+		assert_eq!(
+			GameParams::new(4)
+				.with_repetitions(true)
+				.with_leading_zero(false)
+				.size(),
+			Some(9000)
+		);
+		assert_eq!(GameParams::new(4).with_base(3).size(), Some(0));
+		assert_eq!(
+			GameParams::new(10).with_base(16).index_space_size(),
+			Some(29059430400)
+		);
+		assert_eq!(
+			GameParams::new(36)
+				.with_base(36)
+				.with_repetitions(true)
+				.size(),
+			None
+		);
+	}
+
+	#[test]
+	fn test_random_number() {
+		let g = GameParams::new(3)
+			.with_base(4)
+			.with_max_repetitions(2)
+			.with_leading_zero(false);
+		let mut random = Random::new(1);
+		let mut found = std::collections::HashSet::new();
+		for _ in 0..1000 {
+			let x = g.random_number(&mut random).unwrap();
+			assert!(g.is_valid(&x));
+			found.insert(x);
+		}
+		assert_eq!(found.len() as u64, g.size().unwrap());
+
+		let g = GameParams::new(4).with_leading_zero(false);
+		assert!(g.is_valid(&g.random_number(&mut random).unwrap()));
+		// The indexes don't fit into u64
+		let g = GameParams::new(20).with_base(36);
+		assert_eq!(g.index_space_size(), None);
+		assert!(g.is_valid(&g.random_number(&mut random).unwrap()));
+		assert_eq!(
+			GameParams::new(4).with_base(3).random_number(&mut random),
+			None
+		);
+	}
+
 	#[test]
 	fn test_gen_numbers() {
 		{
@@ -0,0 +1,214 @@
+use std::io::{BufRead, Write};
+
+use crate::game_utils::{GameParams, Number};
+use crate::json::Value;
+use crate::solver::{Solver, COUNT_LIMIT};
+use crate::strategy::Strategy;
+
+// Event of the game with the user. In the machine-readable mode every event is printed as a JSON line
+enum GameEvent {
+	// Count of numbers consistent with the responses. It isn't exact if the limit of counting is reached
+	Candidates {
+		count: u64,
+		exact: bool,
+	},
+	Guess {
+		number: i32,
+		guess: Number,
+		report: Option<String>,
+	},
+	Win {
+		guesses: i32,
+	},
+	Inconsistent,
+	InvalidResponse(String),
+}
+
+impl GameEvent {
+	fn to_json(&self) -> Value {
+		match self {
+			GameEvent::Candidates { count, exact } => Value::object([
+				("event", "candidates".into()),
+				("count", (*count).into()),
+				("exact", (*exact).into()),
+			]),
+			GameEvent::Guess {
+				number,
+				guess,
+				report,
+			} => Value::object([
+				("event", "guess".into()),
+				("number", (*number).into()),
+				("guess", guess.to_string().into()),
+				("report", report.clone().map_or(Value::Null, Value::from)),
+			]),
+			GameEvent::Win { guesses } => {
+				Value::object([("event", "win".into()), ("guesses", (*guesses).into())])
+			}
+			GameEvent::Inconsistent => Value::object([("event", "inconsistent".into())]),
+			GameEvent::InvalidResponse(err) => Value::object([
+				("event", "invalid_response".into()),
+				("message", err.as_str().into()),
+			]),
+		}
+	}
+
+	fn print(&self, output: &mut impl Write, json_mode: bool) -> std::io::Result<()> {
+		if json_mode {
+			writeln!(output, "{}", self.to_json())?;
+			return output.flush();
+		}
+		match self {
+			// The report of the strategy tells about candidates in the text mode
+			GameEvent::Candidates { .. } => {}
+			GameEvent::Guess {
+				number,
+				guess,
+				report,
+			} => {
+				writeln!(output, "Guess #{:?}: {:}", number, guess)?;
+				if let Some(report) = report {
+					writeln!(output, "{}", report)?;
+				}
+			}
+			GameEvent::Win { guesses } => {
+				writeln!(output, "The number is guessed with {} attempts", guesses)?
+			}
+			GameEvent::Inconsistent => writeln!(output, "Answers are inconsistent")?,
+			GameEvent::InvalidResponse(err) => writeln!(output, "{}", err)?,
+		}
+		output.flush()
+	}
+}
+
+// Response "<bulls> <cows>", or {"bulls": <n>, "cows": <n>} in the machine-readable mode
+fn parse_response(line: &str, g: &GameParams, json_mode: bool) -> Result<(u8, u8), String> {
+	let res = if json_mode {
+		let v = Value::parse(line)?;
+		let get = |name| {
+			v.get(name)
+				.and_then(|x| x.as_u64())
+				.and_then(|x| u8::try_from(x).ok())
+		};
+		get("bulls").zip(get("cows"))
+	} else {
+		match line.split_whitespace().collect::<Vec<_>>()[..] {
+			[bulls, cows] => bulls.parse().ok().zip(cows.parse().ok()),
+			_ => None,
+		}
+	};
+	match res {
+		Some((bulls, cows)) if g.is_valid_response(bulls, cows) => Ok((bulls, cows)),
+		_ => Err(format!("Invalid response: {}", line.trim())),
+	}
+}
+
+// Play with the user: the strategy guesses, the user gives responses.
+// The game ends with the win, with inconsistent responses or at the end of input
+pub fn one_game(
+	a: &mut dyn Strategy,
+	g: &GameParams,
+	json_mode: bool,
+	mut input: impl BufRead,
+	mut output: impl Write,
+) -> std::io::Result<()> {
+	a.init();
+	let mut history = Vec::new();
+	let mut counter = 1;
+	loop {
+		// Counting isn't free, so it's done only for the machine-readable mode
+		if json_mode {
+			let count = Solver::new(g, &history).map_or(0, |mut s| s.count(COUNT_LIMIT));
+			let exact = count < COUNT_LIMIT;
+			GameEvent::Candidates { count, exact }.print(&mut output, json_mode)?;
+		}
+		let Some(guess) = a.make_guess().cloned() else {
+			return GameEvent::Inconsistent.print(&mut output, json_mode);
+		};
+		let report = a.last_guess_report();
+		GameEvent::Guess {
+			number: counter,
+			guess: guess.clone(),
+			report,
+		}
+		.print(&mut output, json_mode)?;
+
+		let (bulls, cows) = loop {
+			let mut s = String::new();
+			if input.read_line(&mut s)? == 0 {
+				return Ok(());
+			}
+			match parse_response(&s, g, json_mode) {
+				Ok(res) => break res,
+				Err(err) => GameEvent::InvalidResponse(err).print(&mut output, json_mode)?,
+			}
+		};
+		if bulls == g.number_len() {
+			return GameEvent::Win { guesses: counter }.print(&mut output, json_mode);
+		}
+		a.respond_to_guess(bulls, cows);
+		history.push((guess, bulls, cows));
+		counter += 1;
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::prior::Prior;
+	use crate::strategy::{create_strategy, StrategyType};
+
+	fn run(input: &str, json_mode: bool) -> Vec<String> {
+		let g = GameParams::new(3).with_base(6);
+		let mut s = create_strategy(StrategyType::Naive, &g, &Prior::default()).unwrap();
+		let mut output = Vec::new();
+		one_game(s.as_mut(), &g, json_mode, input.as_bytes(), &mut output).unwrap();
+		String::from_utf8(output)
+			.unwrap()
+			.lines()
+			.map(|x| x.to_owned())
+			.collect()
+	}
+
+	#[test]
+	fn test_json_mode() {
+		let lines = run(
+			"{\"bulls\": 0, \"cows\": 0}\n1 2\n{\"bulls\":1,\"cows\":3}\n{\"bulls\":200,\"cows\":200}\n{\"bulls\":3,\"cows\":0}\n",
+			true,
+		);
+		assert_eq!(
+			lines,
+			vec![
+				r#"{"event":"candidates","count":120,"exact":true}"#,
+				r#"{"event":"guess","number":1,"guess":"012","report":null}"#,
+				r#"{"event":"candidates","count":6,"exact":true}"#,
+				r#"{"event":"guess","number":2,"guess":"345","report":null}"#,
+				r#"{"event":"invalid_response","message":"Invalid JSON at position 2: unexpected data after the value"}"#,
+				r#"{"event":"invalid_response","message":"Invalid response: {\"bulls\":1,\"cows\":3}"}"#,
+				r#"{"event":"invalid_response","message":"Invalid response: {\"bulls\":200,\"cows\":200}"}"#,
+				r#"{"event":"win","guesses":2}"#,
+			]
+		);
+
+		let lines = run(
+			"{\"bulls\": 0, \"cows\": 0}\n{\"bulls\": 0, \"cows\": 0}\n",
+			true,
+		);
+		assert_eq!(lines.last().unwrap(), r#"{"event":"inconsistent"}"#);
+	}
+
+	#[test]
+	fn test_text_mode() {
+		let lines = run("0 0\nabc\n200 200\n3 0\n", false);
+		assert_eq!(
+			lines,
+			vec![
+				"Guess #1: 012",
+				"Guess #2: 345",
+				"Invalid response: abc",
+				"Invalid response: 200 200",
+				"The number is guessed with 2 attempts",
+			]
+		);
+	}
+}
@@ -0,0 +1,416 @@
+use std::fmt::{Display, Formatter};
+
+// Arrays and objects nested deeper are rejected, so the recursive parser can't overflow the stack
+const MAX_NESTING: usize = 64;
+
+// Minimal JSON support for the machine-readable mode.
+// Values are printed in one line, so every value can be sent as a separate line
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+	Null,
+	Bool(bool),
+	Number(f64),
+	String(String),
+	Array(Vec<Value>),
+	// Fields keep their order
+	Object(Vec<(String, Value)>),
+}
+
+impl Value {
+	pub fn object<'a>(fields: impl IntoIterator<Item = (&'a str, Value)>) -> Self {
+		Value::Object(fields.into_iter().map(|(k, v)| (k.to_owned(), v)).collect())
+	}
+
+	// Value of the field of an object, None if there is no such field or it isn't an object
+	pub fn get(&self, key: &str) -> Option<&Value> {
+		match self {
+			Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+			_ => None,
+		}
+	}
+
+	// None if it isn't a non-negative integer
+	pub fn as_u64(&self) -> Option<u64> {
+		match self {
+			Value::Number(x) if *x >= 0.0 && x.fract() == 0.0 && *x <= u64::MAX as f64 => {
+				Some(*x as u64)
+			}
+			_ => None,
+		}
+	}
+
+	pub fn parse(s: &str) -> Result<Value, String> {
+		let mut parser = Parser {
+			s: s.as_bytes(),
+			pos: 0,
+			depth: 0,
+		};
+		let res = parser.value()?;
+		parser.skip_spaces();
+		if parser.pos != parser.s.len() {
+			return Err(parser.error("unexpected data after the value"));
+		}
+		Ok(res)
+	}
+}
+
+impl From<bool> for Value {
+	fn from(x: bool) -> Self {
+		Value::Bool(x)
+	}
+}
+
+impl From<f64> for Value {
+	fn from(x: f64) -> Self {
+		Value::Number(x)
+	}
+}
+
+impl From<u64> for Value {
+	fn from(x: u64) -> Self {
+		Value::Number(x as f64)
+	}
+}
+
+impl From<i32> for Value {
+	fn from(x: i32) -> Self {
+		Value::Number(x as f64)
+	}
+}
+
+impl From<&str> for Value {
+	fn from(x: &str) -> Self {
+		Value::String(x.to_owned())
+	}
+}
+
+impl From<String> for Value {
+	fn from(x: String) -> Self {
+		Value::String(x)
+	}
+}
+
+fn write_string(f: &mut Formatter<'_>, s: &str) -> std::fmt::Result {
+	f.write_str("\"")?;
+	for c in s.chars() {
+		match c {
+			'"' => f.write_str("\\\"")?,
+			'\\' => f.write_str("\\\\")?,
+			'\n' => f.write_str("\\n")?,
+			'\r' => f.write_str("\\r")?,
+			'\t' => f.write_str("\\t")?,
+			c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+			c => write!(f, "{}", c)?,
+		}
+	}
+	f.write_str("\"")
+}
+
+impl Display for Value {
+	fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+		match self {
+			Value::Null => f.write_str("null"),
+			Value::Bool(x) => write!(f, "{}", x),
+			// JSON has no infinities and NaN
+			Value::Number(x) if !x.is_finite() => f.write_str("null"),
+			Value::Number(x) => write!(f, "{}", x),
+			Value::String(s) => write_string(f, s),
+			Value::Array(v) => {
+				f.write_str("[")?;
+				for (i, x) in v.iter().enumerate() {
+					if i > 0 {
+						f.write_str(",")?;
+					}
+					write!(f, "{}", x)?;
+				}
+				f.write_str("]")
+			}
+			Value::Object(fields) => {
+				f.write_str("{")?;
+				for (i, (k, v)) in fields.iter().enumerate() {
+					if i > 0 {
+						f.write_str(",")?;
+					}
+					write_string(f, k)?;
+					write!(f, ":{}", v)?;
+				}
+				f.write_str("}")
+			}
+		}
+	}
+}
+
+struct Parser<'a> {
+	s: &'a [u8],
+	pos: usize,
+	// Count of the arrays and objects the current position is in
+	depth: usize,
+}
+
+impl Parser<'_> {
+	fn error(&self, msg: &str) -> String {
+		format!("Invalid JSON at position {}: {}", self.pos, msg)
+	}
+
+	fn skip_spaces(&mut self) {
+		while self.pos < self.s.len() && self.s[self.pos].is_ascii_whitespace() {
+			self.pos += 1;
+		}
+	}
+
+	fn peek(&mut self) -> Option<u8> {
+		self.skip_spaces();
+		self.s.get(self.pos).copied()
+	}
+
+	fn expect(&mut self, c: u8) -> Result<(), String> {
+		if self.peek() != Some(c) {
+			return Err(self.error(&format!("'{}' is expected", c as char)));
+		}
+		self.pos += 1;
+		Ok(())
+	}
+
+	fn keyword(&mut self, word: &str, value: Value) -> Result<Value, String> {
+		if !self.s[self.pos..].starts_with(word.as_bytes()) {
+			return Err(self.error("unknown value"));
+		}
+		self.pos += word.len();
+		Ok(value)
+	}
+
+	fn value(&mut self) -> Result<Value, String> {
+		match self.peek() {
+			None => Err(self.error("value is expected")),
+			Some(b'n') => self.keyword("null", Value::Null),
+			Some(b't') => self.keyword("true", Value::Bool(true)),
+			Some(b'f') => self.keyword("false", Value::Bool(false)),
+			Some(b'"') => Ok(Value::String(self.string()?)),
+			Some(b'[') => self.nested(Self::array),
+			Some(b'{') => self.nested(Self::object),
+			Some(_) => self.number(),
+		}
+	}
+
+	fn nested(&mut self, parse: fn(&mut Self) -> Result<Value, String>) -> Result<Value, String> {
+		if self.depth == MAX_NESTING {
+			return Err(self.error("too deep nesting"));
+		}
+		self.depth += 1;
+		let res = parse(self);
+		self.depth -= 1;
+		res
+	}
+
+	// The current position is at the opening bracket
+	fn array(&mut self) -> Result<Value, String> {
+		self.pos += 1;
+		let mut res = Vec::new();
+		if self.peek() == Some(b']') {
+			self.pos += 1;
+			return Ok(Value::Array(res));
+		}
+		loop {
+			res.push(self.value()?);
+			if self.peek() == Some(b']') {
+				self.pos += 1;
+				return Ok(Value::Array(res));
+			}
+			self.expect(b',')?;
+		}
+	}
+
+	// The current position is at the opening brace
+	fn object(&mut self) -> Result<Value, String> {
+		self.pos += 1;
+		let mut res = Vec::new();
+		if self.peek() == Some(b'}') {
+			self.pos += 1;
+			return Ok(Value::Object(res));
+		}
+		loop {
+			if self.peek() != Some(b'"') {
+				return Err(self.error("key is expected"));
+			}
+			let key = self.string()?;
+			self.expect(b':')?;
+			res.push((key, self.value()?));
+			if self.peek() == Some(b'}') {
+				self.pos += 1;
+				return Ok(Value::Object(res));
+			}
+			self.expect(b',')?;
+		}
+	}
+
+	// Skip the digits, returns their count
+	fn digits(&mut self) -> usize {
+		let start = self.pos;
+		while self.s.get(self.pos).is_some_and(u8::is_ascii_digit) {
+			self.pos += 1;
+		}
+		self.pos - start
+	}
+
+	// Only the JSON syntax is accepted: no plus sign, leading zeros, or empty integer and fraction parts
+	fn number(&mut self) -> Result<Value, String> {
+		let start = self.pos;
+		if self.s.get(self.pos) == Some(&b'-') {
+			self.pos += 1;
+		}
+		let int_start = self.pos;
+		let mut valid = match self.digits() {
+			0 => false,
+			1 => true,
+			_ => self.s[int_start] != b'0',
+		};
+		if self.s.get(self.pos) == Some(&b'.') {
+			self.pos += 1;
+			valid &= self.digits() > 0;
+		}
+		if matches!(self.s.get(self.pos), Some(b'e' | b'E')) {
+			self.pos += 1;
+			if matches!(self.s.get(self.pos), Some(b'+' | b'-')) {
+				self.pos += 1;
+			}
+			valid &= self.digits() > 0;
+		}
+		std::str::from_utf8(&self.s[start..self.pos])
+			.ok()
+			.filter(|_| valid)
+			.and_then(|x| x.parse().ok())
+			.map(Value::Number)
+			.ok_or_else(|| {
+				self.pos = start;
+				self.error("invalid number")
+			})
+	}
+
+	// The current position is at the opening quote
+	fn string(&mut self) -> Result<String, String> {
+		self.pos += 1;
+		let mut res = Vec::new();
+		loop {
+			let Some(c) = self.s.get(self.pos).copied() else {
+				return Err(self.error("unterminated string"));
+			};
+			self.pos += 1;
+			match c {
+				b'"' => break,
+				b'\\' => {
+					let Some(e) = self.s.get(self.pos).copied() else {
+						return Err(self.error("unterminated string"));
+					};
+					self.pos += 1;
+					let c = match e {
+						b'"' => '"',
+						b'\\' => '\\',
+						b'/' => '/',
+						b'b' => '\u{8}',
+						b'f' => '\u{c}',
+						b'n' => '\n',
+						b'r' => '\r',
+						b't' => '\t',
+						b'u' => {
+							// Surrogate pairs aren't supported, they aren't needed for the protocol
+							let code = self
+								.s
+								.get(self.pos..self.pos + 4)
+								.and_then(|x| std::str::from_utf8(x).ok())
+								.and_then(|x| u32::from_str_radix(x, 16).ok())
+								.and_then(char::from_u32)
+								.ok_or_else(|| self.error("invalid escape"))?;
+							self.pos += 4;
+							code
+						}
+						_ => return Err(self.error("invalid escape")),
+					};
+					let mut buf = [0; 4];
+					res.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+				}
+				c => res.push(c),
+			}
+		}
+		String::from_utf8(res).map_err(|_| self.error("invalid UTF-8"))
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_print_and_parse() {
+		let v = Value::object([
+			("event", "guess".into()),
+			("guess", "01A3".into()),
+			("count", 120u64.into()),
+			("avg", 4.5.into()),
+			("exact", true.into()),
+			(
+				"list",
+				Value::Array(vec![Value::Null, "a\"b\\c\n".into(), (-3).into()]),
+			),
+		]);
+		let s = v.to_string();
+		assert_eq!(
+			s,
+			r#"{"event":"guess","guess":"01A3","count":120,"avg":4.5,"exact":true,"list":[null,"a\"b\\c\n",-3]}"#
+		);
+		assert_eq!(Value::parse(&s), Ok(v));
+
+		let v = Value::parse(" { \"bulls\" : 1, \"cows\":2 , \"x\": \"\\u0041\" } ").unwrap();
+		assert_eq!(v.get("bulls").and_then(|x| x.as_u64()), Some(1));
+		assert_eq!(v.get("cows").and_then(|x| x.as_u64()), Some(2));
+		assert_eq!(v.get("x"), Some(&Value::from("A")));
+		assert_eq!(v.get("y"), None);
+		assert_eq!(Value::parse("[]"), Ok(Value::Array(Vec::new())));
+		assert_eq!(Value::parse("1.5").unwrap().as_u64(), None);
+
+		for s in [
+			"",
+			"{",
+			"{\"a\" 1}",
+			"[1,]",
+			"\"abc",
+			"nul",
+			"1 2",
+			"{1:2}",
+			"--1",
+		] {
+			assert!(Value::parse(s).is_err(), "{}", s);
+		}
+	}
+
+	#[test]
+	fn test_numbers() {
+		for (s, x) in [
+			("0", 0.0),
+			("-0.5e+2", -50.0),
+			("1E3", 1000.0),
+			("10.25", 10.25),
+		] {
+			assert_eq!(Value::parse(s), Ok(Value::Number(x)), "{}", s);
+		}
+		for s in [
+			"1.", "+1", ".5", "01", "-", "1e", "1.e5", "1e+", "0x10", "-01",
+		] {
+			assert!(Value::parse(s).is_err(), "{}", s);
+		}
+	}
+
+	#[test]
+	fn test_nesting() {
+		let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+		assert!(Value::parse(&nested(MAX_NESTING)).is_ok());
+		assert_eq!(
+			Value::parse(&nested(MAX_NESTING + 1)),
+			Err(format!(
+				"Invalid JSON at position {}: too deep nesting",
+				MAX_NESTING
+			))
+		);
+		// Unterminated nesting of any depth is an error, not a stack overflow
+		assert!(Value::parse(&"[{\"a\":".repeat(1 << 20)).is_err());
+	}
+}
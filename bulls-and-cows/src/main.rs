@@ -1,183 +1,436 @@
-use std::{
-	mem,
-	num::NonZero,
-	sync::{mpsc::channel, Arc, Mutex},
-};
+use std::num::NonZero;
 
+use args::{get_arg_value, get_game_params, get_parsed_arg, has_arg};
 use game_utils::Number;
-use strategy::{create_strategy, StrategyType};
+use prior::Prior;
+use strategy::{build_opening_book, create_strategy, StrategyType};
 
+mod args;
+mod bitset;
 mod game_utils;
+mod interactive;
+mod json;
+mod match_runner;
+mod prior;
+mod random;
+mod server;
+mod solver;
 mod strategy;
 
 #[derive(Debug, Default)]
 struct EvaluationResult {
 	total: i32,
+	// Sum of guesses counts multiplied by weights of hidden numbers
+	weighted_total: f64,
 	worst_guess_count: i32,
+	// Average number of guesses if hidden numbers are picked with the probabilities of the prior
 	avg: f64,
 	worst_number: Number,
 	time: std::time::Duration,
 }
 
-fn evaluate_strategy_one_thread(
+// Play the game with the given hidden number, return the number of guesses
+fn play_game(
 	strategy: &mut dyn strategy::Strategy,
-	vals: Arc<Mutex<Vec<Number>>>,
-	game: game_utils::GameParams,
-) -> Result<EvaluationResult, String> {
-	const CHUNK_SIZE: usize = 20;
-
-	let mut total = 0;
-	let mut worst_guess_count = 0;
-	let mut worst_number = Number::default();
+	x: &Number,
+	game: &game_utils::GameParams,
+) -> Result<i32, String> {
+	strategy.init();
 
+	let mut counter = 1;
 	loop {
-		let cur_values;
-		{
-			let mut vals = vals.lock().unwrap();
-			let n = vals.len();
-			if n < CHUNK_SIZE {
-				if n == 0 {
-					break;
+		match strategy.make_guess() {
+			Some(guess) => {
+				if guess == x {
+					return Ok(counter);
 				}
-				cur_values = mem::replace(vals.as_mut(), Vec::new());
-			} else {
-				cur_values = vals.drain(n - CHUNK_SIZE..n).collect();
-			}
-		}
-
-		for x in cur_values {
-			strategy.init();
-
-			let mut counter = 1;
-			loop {
-				total += 1;
-				let guess = strategy.make_guess();
-
-				match guess {
-					Some(guess) => {
-						if guess == &x {
-							break;
-						}
-						counter += 1;
-						if counter > 30 {
-							return Err(format!(
-								"Probably it is an infinite loop. Problem number: {x}"
-							));
-						}
-						let (b, c) = game.calc_bc(guess, &x);
-						strategy.respond_to_guess(b, c);
-					}
-
-					None => {
-						return Err(format!("The strategy returned None. Problem number: {x}"));
-					}
+				counter += 1;
+				if counter > 30 {
+					return Err(format!(
+						"Probably it is an infinite loop. Problem number: {x}"
+					));
 				}
+				let (b, c) = game.calc_bc(guess, x);
+				strategy.respond_to_guess(b, c);
 			}
-			if counter > worst_guess_count {
-				worst_guess_count = counter;
-				worst_number = x;
+
+			None => {
+				return Err(format!("The strategy returned None. Problem number: {x}"));
 			}
 		}
 	}
-
-	Ok(EvaluationResult {
-		total,
-		worst_guess_count,
-		worst_number,
-		avg: 0.0,
-		time: Default::default(),
-	})
 }
 
 fn evaluate_strategy(
 	strategy: &mut dyn strategy::Strategy,
 	game: &game_utils::GameParams,
+	prior: &Prior,
 ) -> Result<EvaluationResult, String> {
 	let start_time = std::time::Instant::now();
 
-	let vals: Vec<_> = game_utils::get_numbers_iter(game).collect();
-	let numbers_count = vals.len() as f64;
-
-	let vals = Mutex::new(vals);
-	let vals = Arc::new(vals);
+	// Every thread plays with its own copy of the strategy, the results are summed up at the end
+	let states = (0..std::thread::available_parallelism()
+		.unwrap_or(NonZero::new(1).unwrap())
+		.get())
+		.map(|_| (strategy.clone_strategy(), EvaluationResult::default(), 0.0))
+		.collect();
 
-	let (sx, rx) = channel();
+	let states = game_utils::par_for_each_number(
+		game,
+		states,
+		|(strategy, res, total_weight), x| -> Result<(), String> {
+			// Numbers with zero weight are never hidden, so they don't affect the result
+			let weight = prior.weight(x);
+			if weight == 0.0 {
+				return Ok(());
+			}
+			let counter = play_game(strategy.as_mut(), x, game)?;
+			res.total += counter;
+			res.weighted_total += weight * counter as f64;
+			*total_weight += weight;
+			if counter > res.worst_guess_count {
+				res.worst_guess_count = counter;
+				res.worst_number = x.clone();
+			}
+			Ok(())
+		},
+	)?;
 
-	let mut join_handles = Vec::new();
-	for _ in 0..std::thread::available_parallelism()
-		.unwrap_or(NonZero::new(1).unwrap())
-		.get()
-	{
-		let sx = sx.clone();
-		let vals = vals.clone();
-		let mut strategy = strategy.clone_strategy();
-		let g = game.clone();
-		join_handles.push(std::thread::spawn(move || {
-			let res = evaluate_strategy_one_thread(strategy.as_mut(), vals, g);
-			sx.send(res).unwrap();
-		}));
-	}
-	drop(sx);
 	let mut res = EvaluationResult::default();
-	for res_partial in rx {
-		match res_partial {
-			Ok(res_partial) => {
-				res.total += res_partial.total;
-				if res.worst_guess_count < res_partial.worst_guess_count {
-					res.worst_guess_count = res_partial.worst_guess_count;
-					res.worst_number = res_partial.worst_number;
-				}
+	let mut total_weight = 0.0;
+	for (_, res_partial, weight) in states {
+		res.total += res_partial.total;
+		res.weighted_total += res_partial.weighted_total;
+		total_weight += weight;
+		if res.worst_guess_count < res_partial.worst_guess_count {
+			res.worst_guess_count = res_partial.worst_guess_count;
+			res.worst_number = res_partial.worst_number;
+		}
+	}
+
+	res.avg = res.weighted_total / total_weight;
+	res.time = std::time::Instant::now() - start_time;
+	Ok(res)
+}
+
+const ALL_STRATEGIES: [StrategyType; 7] = [
+	StrategyType::Naive,
+	StrategyType::AmountInformation,
+	StrategyType::MinMax,
+	StrategyType::Landy,
+	StrategyType::MinAvg,
+	StrategyType::Sampling,
+	StrategyType::WinChance,
+];
+
+fn generate_openings(g: &game_utils::GameParams, prior: &Prior) {
+	// The naive and the sampling strategies are fast enough without an opening book,
+	// WinChance doesn't use it because its choice depends on the opponent
+	for st in ALL_STRATEGIES.into_iter().filter(|st| {
+		!matches!(
+			st,
+			StrategyType::Naive | StrategyType::Sampling | StrategyType::WinChance
+		)
+	}) {
+		let start_time = std::time::Instant::now();
+		let book = match build_opening_book(st, g, prior) {
+			Ok(Some(book)) => book,
+			Ok(None) => {
+				println!("Strategy type: {:?} isn't able to make the first guess", st);
+				continue;
 			}
 			Err(err) => {
-				vals.lock().unwrap().clear();
-				join_handles.into_iter().for_each(|x| x.join().unwrap());
-				return Err(err);
+				println!("Strategy type: {:?}, {}", st, err);
+				continue;
 			}
+		};
+		match book.save(st, g, prior) {
+			Ok(()) => println!(
+				"Strategy type: {:?}, first guess {:}, {:} replies saved to {:?} in {:?}",
+				st,
+				book.first_guess(),
+				book.replies_count(),
+				strategy::OpeningBook::path(st, g, prior),
+				std::time::Instant::now() - start_time
+			),
+			Err(err) => println!(
+				"Strategy type: {:?}, unable to save the opening book: {:}",
+				st, err
+			),
 		}
 	}
-	join_handles.into_iter().for_each(|x| x.join().unwrap());
+}
 
-	res.avg = res.total as f64 / numbers_count;
-	res.time = std::time::Instant::now() - start_time;
-	Ok(res)
+const DEFAULT_ROUNDS: u32 = 100;
+
+// Player number i (1 or 2) of the match. Random keepers of the players hide different numbers
+fn create_player(
+	st: StrategyType,
+	policy: &str,
+	i: u64,
+	seed: u64,
+	g: &game_utils::GameParams,
+	prior: &Prior,
+) -> Result<match_runner::Player, String> {
+	Ok(match_runner::Player {
+		strategy: create_strategy(st, g, prior)?,
+		keeper: match_runner::create_keeper(policy, g, seed.wrapping_mul(2).wrapping_add(i))?,
+		name: format!("{:?}", st),
+	})
 }
 
-fn one_game(a: &mut dyn strategy::Strategy) {
-	a.init();
-	let mut counter = 1;
-	loop {
-		if let Some(guess) = a.make_guess() {
-			println!("Guess #{:?}: {:}", counter, guess);
-		} else {
-			println!("Answers are inconsistent");
-			break;
-		}
-		counter += 1;
-		let mut s = String::new();
-		std::io::stdin().read_line(&mut s).unwrap();
-		let v: Vec<_> = s.split(' ').map(|x| x.trim().parse().unwrap()).collect();
-		if v.len() == 2 {
-			a.respond_to_guess(v[0], v[1]);
+// Play the symmetric game between two strategies and print win rates.
+// Players are given by --strategy1, --keeper1, --strategy2 and --keeper2, see match_runner::create_keeper
+fn run_match(args: &[String], g: &game_utils::GameParams, prior: &Prior) -> Result<(), String> {
+	let rounds = get_parsed_arg(args, "--rounds", DEFAULT_ROUNDS)?;
+	let seed = get_parsed_arg(args, "--seed", 1)?;
+	let player = |i: u64| -> Result<match_runner::Player, String> {
+		let name = get_arg_value(args, &format!("--strategy{}", i)).unwrap_or("minavg".to_owned());
+		let st = StrategyType::from_name(&name).ok_or(format!("Unknown strategy {}", name))?;
+		let policy = get_arg_value(args, &format!("--keeper{}", i)).unwrap_or("random".to_owned());
+		create_player(st, &policy, i, seed, g, prior)
+	};
+	let mut players = [player(1)?, player(2)?];
+
+	let start_time = std::time::Instant::now();
+	let res = match_runner::play_match(&mut players, g, rounds);
+	println!("Game {}, {} rounds", g, rounds);
+	for (i, p) in players.iter().enumerate() {
+		println!(
+			"Player {}: {}, keeper {}. Wins {} ({:.2}%), failures {}",
+			i + 1,
+			p.name,
+			p.keeper.name(),
+			res.wins[i],
+			res.win_rate(i) * 100.0,
+			res.failures[i]
+		);
+	}
+	println!("Draws: {}", res.draws);
+	match res.avg_winning_guesses() {
+		Some(avg) => println!("Average guesses of the winner: {:.4}", avg),
+		None => println!("Average guesses of the winner: n/a"),
+	}
+	println!("Total time: {:?}", std::time::Instant::now() - start_time);
+	Ok(())
+}
+
+// Play matches of every strategy against MinAvg and print win rates.
+// Both players guess the same random secrets, so the results depend only on the strategies
+fn analyze_matches(
+	args: &[String],
+	g: &game_utils::GameParams,
+	prior: &Prior,
+) -> Result<(), String> {
+	let rounds = get_parsed_arg(args, "--rounds", DEFAULT_ROUNDS)?;
+	let seed = get_parsed_arg(args, "--seed", 1)?;
+	println!("Game {}, {} rounds against MinAvg\n", g, rounds);
+	for st in ALL_STRATEGIES {
+		let players = create_player(st, "random", 1, seed, g, prior).and_then(|a| {
+			Ok([
+				a,
+				create_player(StrategyType::MinAvg, "random", 1, seed, g, prior)?,
+			])
+		});
+		let mut players = match players {
+			Ok(players) => players,
+			Err(err) => {
+				println!("Strategy type: {:?}, {}\n", st, err);
+				continue;
+			}
+		};
+		let start_time = std::time::Instant::now();
+		let res = match_runner::play_match(&mut players, g, rounds);
+		println!(
+			"Strategy type: {:?}. Wins {:.2}%, losses {:.2}%, draws {:.2}%",
+			st,
+			res.win_rate(0) * 100.0,
+			res.win_rate(1) * 100.0,
+			res.draws as f64 / rounds as f64 * 100.0
+		);
+		println!("Total time: {:?}\n", std::time::Instant::now() - start_time);
+	}
+	Ok(())
+}
+
+// Read responses "<guess> <bulls> <cows>" from stdin, one per line
+fn read_history(g: &game_utils::GameParams) -> Result<Vec<(Number, u8, u8)>, String> {
+	let mut history = Vec::new();
+	for line in std::io::stdin().lines() {
+		let line = line.map_err(|err| err.to_string())?;
+		let v: Vec<_> = line.split_whitespace().collect();
+		if v.is_empty() {
+			continue;
+		}
+		let response = match v[..] {
+			[guess, bulls, cows] => g
+				.to_number_checked(&guess.to_uppercase())
+				.zip(bulls.parse::<u8>().ok())
+				.zip(cows.parse::<u8>().ok())
+				.map(|((guess, bulls), cows)| (guess, bulls, cows))
+				.filter(|(_, bulls, cows)| g.is_valid_response(*bulls, *cows)),
+			_ => None,
+		};
+		history.push(response.ok_or(format!("Invalid response: {}", line))?);
+	}
+	Ok(history)
+}
+
+// Read responses from stdin and print the numbers that are consistent with all of them
+fn check_consistency(args: &[String], g: &game_utils::GameParams) -> Result<(), String> {
+	const DEFAULT_LIMIT: usize = 20;
+
+	let limit = get_parsed_arg(args, "--limit", DEFAULT_LIMIT)?;
+	let history = read_history(g)?;
+	let mut solver = solver::Solver::new(g, &history)?;
+	for pos in 0..g.number_len() as usize {
+		// Allowed digits are printed as one number
+		let domain = Number::from_digits(solver.domain(pos));
+		println!("Position {}: {}", pos + 1, domain);
+	}
+	let count = solver.count(solver::COUNT_LIMIT);
+	if count == 0 {
+		// The first response that can't be satisfied together with the previous ones
+		let k = (1..=history.len())
+			.find(|k| {
+				solver::Solver::new(g, &history[..*k]).map_or(true, |mut s| s.first().is_none())
+			})
+			.unwrap_or(0);
+		println!(
+			"Answers are inconsistent, the response #{} contradicts the previous ones",
+			k
+		);
+		return Ok(());
+	}
+
+	if count == solver::COUNT_LIMIT {
+		println!("At least {} consistent numbers", count);
+	} else {
+		println!("{} consistent numbers", count);
+	}
+	let mut numbers = Vec::new();
+	solver.for_each(|x| {
+		numbers.push(x.to_string());
+		numbers.len() < limit
+	});
+	println!("{}", numbers.join(" "));
+	Ok(())
+}
+
+// Read the transcript of a finished game from stdin and print how good every move was
+fn replay(g: &game_utils::GameParams, prior: &Prior) -> Result<(), String> {
+	let history = read_history(g)?;
+	let moves = strategy::analyze_transcript(g, prior, &history)?;
+	for (i, m) in moves.iter().enumerate() {
+		println!(
+			"Move #{}: {} {} {}, candidates {} -> {}, the response gave {:.4} bits",
+			i + 1,
+			m.guess,
+			m.bulls,
+			m.cows,
+			m.candidates,
+			m.candidates_after,
+			m.gained_bits()
+		);
+		if !m.is_candidate {
+			println!("  The guess can't be the hidden number");
+		}
+		if m.lost_information() {
+			println!(
+				"  Lost {:.4} bits: {:.4} bits expected, the best attempt gives {:.4} bits",
+				m.lost_bits(),
+				m.expected_bits,
+				m.best_bits
+			);
 		}
+		for s in m.scores.iter() {
+			if s.is_best {
+				println!("  {:?}: {}, the best", s.strategy, s.value);
+			} else {
+				println!(
+					"  {:?}: {}, the best is {} with {}",
+					s.strategy, s.value, s.best_guess, s.best_value
+				);
+			}
+		}
+	}
+	let lost: Vec<_> = (1..)
+		.zip(moves.iter())
+		.filter(|(_, m)| m.lost_information())
+		.map(|(i, _)| format!("#{}", i))
+		.collect();
+	if lost.is_empty() {
+		println!("No move lost information");
+	} else {
+		println!("Moves that lost information: {}", lost.join(", "));
 	}
+	Ok(())
 }
 
 fn main() {
-	const N: u8 = 4;
+	let args: Vec<_> = std::env::args().collect();
+	let g = match get_game_params(&args) {
+		Ok(g) => g,
+		Err(err) => {
+			println!("{}", err);
+			return;
+		}
+	};
 
-	let g = game_utils::GameParams::new(N as u8);
+	let prior = match get_arg_value(&args, "--prior") {
+		Some(path) => match Prior::load(std::path::Path::new(&path), &g) {
+			Ok(p) => p,
+			Err(err) => {
+				println!("Unable to load the prior: {}", err);
+				return;
+			}
+		},
+		None => Prior::default(),
+	};
 
-	if std::env::args().position(|x| x == "--analyze").is_some() {
-		for st in [
-			StrategyType::Naive,
-			StrategyType::AmountInformation,
-			StrategyType::MinMax,
-			StrategyType::Landy,
-			StrategyType::MinAvg,
-		] {
-			let mut s = create_strategy(st, &g);
+	let command = args.get(1).map(|x| x.as_str());
+	if command == Some("openings") {
+		generate_openings(&g, &prior);
+	} else if command == Some("serve") {
+		// Parameters of games are given by the clients, see server::Server
+		let server = std::sync::Arc::new(server::Server::new());
+		let res = match get_arg_value(&args, "--listen") {
+			Some(addr) => std::net::TcpListener::bind(addr).and_then(|l| server.listen(l)),
+			None => server.serve(std::io::stdin().lock(), std::io::stdout()),
+		};
+		if let Err(err) = res {
+			println!("{}", err);
+		}
+	} else if command == Some("match") {
+		if let Err(err) = run_match(&args, &g, &prior) {
+			println!("{}", err);
+		}
+	} else if command == Some("check-consistency") {
+		if let Err(err) = check_consistency(&args, &g) {
+			println!("{}", err);
+		}
+	} else if command == Some("replay") {
+		if let Err(err) = replay(&g, &prior) {
+			println!("{}", err);
+		}
+	} else if has_arg(&args, "--analyze-matches") {
+		if let Err(err) = analyze_matches(&args, &g, &prior) {
+			println!("{}", err);
+		}
+	} else if has_arg(&args, "--analyze") {
+		if let Some(size) = g.size() {
+			println!("Game {}, {} possible numbers\n", g, size);
+		}
+		for st in ALL_STRATEGIES {
+			let mut s = match create_strategy(st, &g, &prior) {
+				Ok(s) => s,
+				Err(err) => {
+					println!("Strategy type: {:?}, {}\n", st, err);
+					continue;
+				}
+			};
 
-			match evaluate_strategy(s.as_mut(), &g) {
+			match evaluate_strategy(s.as_mut(), &g, &prior) {
 				Ok(res) => {
 					println!("Strategy type: {:?}, check successfull. Results", st);
 					println!(
@@ -197,8 +450,35 @@ fn main() {
 			}
 		}
 	} else {
-		let mut s = create_strategy(StrategyType::Naive, &g);
+		let st = match get_arg_value(&args, "--strategy") {
+			Some(name) => match StrategyType::from_name(&name) {
+				Some(st) => st,
+				None => {
+					println!("Unknown strategy {}", name);
+					return;
+				}
+			},
+			None => StrategyType::Naive,
+		};
+		let mut s = match create_strategy(st, &g, &prior) {
+			Ok(s) => s,
+			Err(err) => {
+				println!("Strategy type: {:?}, {}", st, err);
+				return;
+			}
+		};
 
-		one_game(s.as_mut());
+		// In the machine-readable mode events and responses are JSON lines
+		let json_mode = has_arg(&args, "--json");
+		let res = interactive::one_game(
+			s.as_mut(),
+			&g,
+			json_mode,
+			std::io::stdin().lock(),
+			std::io::stdout(),
+		);
+		if let Err(err) = res {
+			println!("{}", err);
+		}
 	}
 }
@@ -0,0 +1,395 @@
+use std::collections::HashMap;
+
+use crate::game_utils::{get_numbers_iter, GameParams, Number};
+use crate::random::Random;
+use crate::strategy::{OpponentProgress, Strategy};
+
+// A player that guesses more is considered failed
+const MAX_GUESSES: i32 = 30;
+
+// The adversarial keeper keeps all numbers in memory, so it doesn't support bigger games
+const MAX_ADVERSARIAL_GAME_SIZE: u64 = 1 << 20;
+
+// Policy of the player that hides a number and responds to the guesses of the opponent
+pub trait SecretKeeper: Send {
+	// Start a new round
+	fn init(&mut self);
+
+	fn respond(&mut self, guess: &Number) -> (u8, u8);
+
+	fn name(&self) -> String;
+}
+
+// Hides a random number every round
+pub struct RandomKeeper {
+	game: GameParams,
+	random: Random,
+	secret: Number,
+}
+
+impl RandomKeeper {
+	pub fn new(game: GameParams, seed: u64) -> Self {
+		Self {
+			game,
+			random: Random::new(seed),
+			secret: Number::default(),
+		}
+	}
+}
+
+impl SecretKeeper for RandomKeeper {
+	fn init(&mut self) {
+		self.secret = self.game.random_number(&mut self.random).unwrap();
+	}
+
+	fn respond(&mut self, guess: &Number) -> (u8, u8) {
+		self.game.calc_bc(guess, &self.secret)
+	}
+
+	fn name(&self) -> String {
+		"random".to_owned()
+	}
+}
+
+// Hides the numbers of the list one by one, the list is repeated if there are more rounds
+pub struct FixedKeeper {
+	game: GameParams,
+	secrets: Vec<Number>,
+	rounds: usize,
+}
+
+impl FixedKeeper {
+	pub fn new(game: GameParams, secrets: Vec<Number>) -> Result<Self, String> {
+		if secrets.is_empty() {
+			return Err("the list of secrets is empty".to_owned());
+		}
+		Ok(Self {
+			game,
+			secrets,
+			rounds: 0,
+		})
+	}
+}
+
+impl SecretKeeper for FixedKeeper {
+	fn init(&mut self) {
+		self.rounds += 1;
+	}
+
+	fn respond(&mut self, guess: &Number) -> (u8, u8) {
+		let secret = &self.secrets[(self.rounds - 1) % self.secrets.len()];
+		self.game.calc_bc(guess, secret)
+	}
+
+	fn name(&self) -> String {
+		"fixed".to_owned()
+	}
+}
+
+// Doesn't choose the number in advance. Every response keeps as many candidates as possible,
+// so the number is guessed only when it is the last candidate.
+// The responses are always consistent, so the number may be chosen at the end
+pub struct AdversarialKeeper {
+	game: GameParams,
+	all_numbers: Vec<Number>,
+	candidates: Vec<Number>,
+}
+
+impl AdversarialKeeper {
+	pub fn new(game: GameParams) -> Result<Self, String> {
+		if game.size().is_none_or(|x| x > MAX_ADVERSARIAL_GAME_SIZE) {
+			return Err(format!(
+				"the game {} is too big for the adversarial keeper",
+				game
+			));
+		}
+		Ok(Self {
+			game,
+			all_numbers: get_numbers_iter(&game).collect(),
+			candidates: Vec::new(),
+		})
+	}
+}
+
+impl SecretKeeper for AdversarialKeeper {
+	fn init(&mut self) {
+		self.candidates = self.all_numbers.clone();
+	}
+
+	fn respond(&mut self, guess: &Number) -> (u8, u8) {
+		let mut counts = HashMap::new();
+		for x in self.candidates.iter() {
+			*counts.entry(self.game.calc_bc(guess, x)).or_insert(0) += 1;
+		}
+		// The biggest bucket, the one with less bulls and cows if there are several of them
+		let response = counts
+			.into_iter()
+			.max_by_key(|(response, count)| (*count, std::cmp::Reverse(*response)))
+			.unwrap()
+			.0;
+		self.candidates
+			.retain(|x| self.game.calc_bc(guess, x) == response);
+		response
+	}
+
+	fn name(&self) -> String {
+		"adversarial".to_owned()
+	}
+}
+
+// Keeper by the name of the policy: "random", "adversarial" or a list of numbers separated by commas
+pub fn create_keeper(
+	policy: &str,
+	game: &GameParams,
+	seed: u64,
+) -> Result<Box<dyn SecretKeeper>, String> {
+	match policy {
+		"random" => Ok(Box::new(RandomKeeper::new(*game, seed))),
+		"adversarial" => Ok(Box::new(AdversarialKeeper::new(*game)?)),
+		_ => {
+			let secrets = policy
+				.split(',')
+				.map(|x| {
+					game.to_number_checked(&x.to_uppercase())
+						.ok_or(format!("invalid number {}", x))
+				})
+				.collect::<Result<_, _>>()?;
+			Ok(Box::new(FixedKeeper::new(*game, secrets)?))
+		}
+	}
+}
+
+pub struct Player {
+	pub strategy: Box<dyn Strategy>,
+	pub keeper: Box<dyn SecretKeeper>,
+	pub name: String,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct MatchResult {
+	pub rounds: u32,
+	pub wins: [u32; 2],
+	pub draws: u32,
+	// Rounds where the player failed: its strategy found the responses inconsistent or it needed too many guesses
+	pub failures: [u32; 2],
+	// Rounds where at least one player guessed the number
+	pub finished: u32,
+	// Total count of guesses of the winners in finished rounds, in a draw both players make the same count
+	pub winning_guesses: u64,
+}
+
+impl MatchResult {
+	// Share of the won rounds, 0 if no round was played
+	pub fn win_rate(&self, player: usize) -> f64 {
+		self.wins[player] as f64 / self.rounds.max(1) as f64
+	}
+
+	// Average count of guesses of the winners, None if no round was finished
+	pub fn avg_winning_guesses(&self) -> Option<f64> {
+		(self.finished != 0).then(|| self.winning_guesses as f64 / self.finished as f64)
+	}
+}
+
+// State of one side in the round: it guesses the number hidden by the keeper
+struct Side<'a> {
+	strategy: &'a mut dyn Strategy,
+	keeper: &'a mut dyn SecretKeeper,
+	guesses: i32,
+	solved: bool,
+	failed: bool,
+}
+
+impl Side<'_> {
+	fn make_turn(&mut self, game: &GameParams) {
+		if self.solved || self.failed {
+			return;
+		}
+		self.guesses += 1;
+		let Some(guess) = self.strategy.make_guess().cloned() else {
+			self.failed = true;
+			return;
+		};
+		let (bulls, cows) = self.keeper.respond(&guess);
+		if bulls == game.number_len() {
+			self.solved = true;
+		} else if self.guesses == MAX_GUESSES {
+			self.failed = true;
+		} else {
+			self.strategy.respond_to_guess(bulls, cows);
+		}
+	}
+}
+
+// Play rounds of the symmetric game: both players guess simultaneously, the one who guesses the number
+// in less turns wins, the round is a draw if both of them do it on the same turn.
+// After every turn the players learn the progress of the opponent: the count of its candidates if its strategy
+// keeps them, otherwise the count of its guesses.
+// Every round the keepers change sides: in even rounds a player guesses the number hidden by the keeper
+// of the opponent, in odd rounds the one hidden by its own keeper. So both strategies play against
+// both policies equally often
+pub fn play_match(players: &mut [Player; 2], game: &GameParams, rounds: u32) -> MatchResult {
+	let mut res = MatchResult {
+		rounds,
+		..Default::default()
+	};
+	for round in 0..rounds {
+		let [a, b] = players;
+		a.keeper.init();
+		b.keeper.init();
+		let (keeper_a, keeper_b) = if round % 2 == 0 {
+			(b.keeper.as_mut(), a.keeper.as_mut())
+		} else {
+			(a.keeper.as_mut(), b.keeper.as_mut())
+		};
+		let mut sides = [
+			Side {
+				strategy: a.strategy.as_mut(),
+				keeper: keeper_a,
+				guesses: 0,
+				solved: false,
+				failed: false,
+			},
+			Side {
+				strategy: b.strategy.as_mut(),
+				keeper: keeper_b,
+				guesses: 0,
+				solved: false,
+				failed: false,
+			},
+		];
+		for side in sides.iter_mut() {
+			side.strategy.init();
+		}
+
+		while !sides.iter().any(|x| x.solved) && !sides.iter().all(|x| x.failed) {
+			for side in sides.iter_mut() {
+				side.make_turn(game);
+			}
+			// Every player sees the responses to the opponent. The strategies that keep the candidates
+			// report their count, so the players don't have to recount them
+			let progress: Vec<_> = sides
+				.iter()
+				.map(|x| match x.strategy.candidates_count() {
+					Some(count) => OpponentProgress::Candidates(count),
+					None => OpponentProgress::Guesses(x.guesses as u32),
+				})
+				.collect();
+			sides[0].strategy.set_opponent_progress(progress[1]);
+			sides[1].strategy.set_opponent_progress(progress[0]);
+		}
+
+		for (i, side) in sides.iter().enumerate() {
+			if side.failed {
+				res.failures[i] += 1;
+			}
+		}
+		match (sides[0].solved, sides[1].solved) {
+			(true, false) => res.wins[0] += 1,
+			(false, true) => res.wins[1] += 1,
+			_ => res.draws += 1,
+		}
+		if let Some(side) = sides.iter().find(|x| x.solved) {
+			res.finished += 1;
+			res.winning_guesses += side.guesses as u64;
+		}
+	}
+	res
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::prior::Prior;
+	use crate::strategy::{create_strategy, StrategyType};
+
+	fn player(st: StrategyType, keeper: Box<dyn SecretKeeper>, g: &GameParams) -> Player {
+		Player {
+			strategy: create_strategy(st, g, &Prior::default()).unwrap(),
+			keeper,
+			name: format!("{:?}", st),
+		}
+	}
+
+	#[test]
+	fn test_keepers() {
+		let g = GameParams::new(3).with_base(6);
+		let mut fixed =
+			FixedKeeper::new(g, vec![Number::from("012"), Number::from("345")]).unwrap();
+		for secret in ["012", "345", "012"] {
+			fixed.init();
+			assert_eq!(fixed.respond(&Number::from(secret)), (3, 0));
+		}
+		assert!(FixedKeeper::new(g, Vec::new()).is_err());
+		assert_eq!(create_keeper("012,345", &g, 1).unwrap().name(), "fixed");
+		assert!(create_keeper("012,335", &g, 1).is_err());
+
+		let mut random = RandomKeeper::new(g, 1);
+		random.init();
+		let secret = get_numbers_iter(&g)
+			.find(|x| random.respond(x) == (3, 0))
+			.unwrap();
+		assert_eq!(random.respond(&secret), (3, 0));
+
+		// The adversarial keeper avoids the win while it is possible
+		let mut adversarial = AdversarialKeeper::new(g).unwrap();
+		adversarial.init();
+		let mut count = 0;
+		for x in get_numbers_iter(&g) {
+			count += 1;
+			if adversarial.respond(&x) == (3, 0) {
+				break;
+			}
+		}
+		assert!(count > 5);
+		assert!(AdversarialKeeper::new(GameParams::new(8).with_base(16)).is_err());
+	}
+
+	#[test]
+	fn test_match() {
+		let g = GameParams::new(3).with_base(6);
+		let fixed = || Box::new(FixedKeeper::new(g, vec![Number::from("543")]).unwrap());
+
+		// The same strategies against the same secrets always make a draw
+		let mut players = [
+			player(StrategyType::MinAvg, fixed(), &g),
+			player(StrategyType::MinAvg, fixed(), &g),
+		];
+		let res = play_match(&mut players, &g, 4);
+		assert_eq!(res.draws, 4);
+		assert!(res.avg_winning_guesses().is_some());
+		let res = play_match(&mut players, &g, 0);
+		assert_eq!(res.avg_winning_guesses(), None);
+		assert_eq!(res.win_rate(0), 0.0);
+
+		let mut players = [
+			player(StrategyType::MinAvg, Box::new(RandomKeeper::new(g, 1)), &g),
+			player(
+				StrategyType::Naive,
+				Box::new(AdversarialKeeper::new(g).unwrap()),
+				&g,
+			),
+		];
+		let res = play_match(&mut players, &g, 40);
+		assert_eq!(res.wins[0] + res.wins[1] + res.draws, 40);
+		assert_eq!(res.failures, [0, 0]);
+		assert!(res.wins[0] > res.wins[1]);
+	}
+
+	#[test]
+	fn test_progress() {
+		// The progress reported by the strategy agrees with the count of the solver
+		let g = GameParams::new(3).with_base(6);
+		let mut s = create_strategy(StrategyType::MinAvg, &g, &Prior::default()).unwrap();
+		s.init();
+		let guess = s.make_guess().unwrap().clone();
+		s.respond_to_guess(1, 1);
+		let history = [(guess, 1, 1)];
+		let count = crate::solver::Solver::new(&g, &history)
+			.unwrap()
+			.count(1000);
+		assert_eq!(s.candidates_count(), Some(count));
+
+		let naive = create_strategy(StrategyType::Naive, &g, &Prior::default()).unwrap();
+		assert_eq!(naive.candidates_count(), None);
+	}
+}
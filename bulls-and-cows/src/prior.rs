@@ -0,0 +1,155 @@
+use std::collections::BTreeMap;
+use std::io::BufRead;
+
+use crate::game_utils::{self, GameParams, Number};
+
+// Probability distribution over hidden numbers, up to a constant factor.
+// The weight of a number is the default weight plus the weight listed for it.
+// A list of secrets from recorded games, one per line, gives the distribution learned from these games
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prior {
+	default_weight: f64,
+	weights: BTreeMap<Number, f64>,
+}
+
+impl Default for Prior {
+	fn default() -> Self {
+		Self {
+			default_weight: 1.0,
+			weights: BTreeMap::new(),
+		}
+	}
+}
+
+impl Prior {
+	// True if no number is more likely than the rest, e.g. the listed weights are all zero
+	pub fn is_uniform(&self) -> bool {
+		!self.weights.values().any(|w| *w > 0.0)
+	}
+
+	pub fn weight(&self, num: &Number) -> f64 {
+		self.default_weight + self.weights.get(num).unwrap_or(&0.0)
+	}
+
+	pub fn default_weight(&self) -> f64 {
+		self.default_weight
+	}
+
+	// Numbers that are more likely than the rest, with their weights
+	pub fn preferred(&self) -> impl Iterator<Item = (&Number, f64)> {
+		self.weights
+			.iter()
+			.filter(|(_, w)| **w > 0.0)
+			.map(|(n, w)| (n, self.default_weight + w))
+	}
+
+	// Weights of the given numbers, scaled so that their average is 1.
+	// If all of them have zero weight, the prior says nothing about them, so they are equally likely
+	pub fn normalized_weights(&self, numbers: &[Number]) -> Vec<f64> {
+		let weights: Vec<_> = numbers.iter().map(|x| self.weight(x)).collect();
+		let sum: f64 = weights.iter().sum();
+		if sum <= 0.0 {
+			return vec![1.0; numbers.len()];
+		}
+		let k = numbers.len() as f64 / sum;
+		weights.into_iter().map(|w| w * k).collect()
+	}
+
+	// Short string that identifies the distribution
+	pub fn fingerprint(&self) -> String {
+		if self.is_uniform() {
+			return "uniform".to_owned();
+		}
+		let lines: Vec<_> = std::iter::once(format!("default {}", self.default_weight))
+			.chain(self.weights.iter().map(|(n, w)| format!("{} {}", n, w)))
+			.collect();
+		format!("{:016x}", game_utils::calc_checksum(&lines))
+	}
+
+	pub fn load(path: &std::path::Path, g: &GameParams) -> Result<Self, String> {
+		let f = std::fs::File::open(path).map_err(|e| format!("{:?}: {}", path, e))?;
+		Self::read(f, g).map_err(|e| format!("{:?}: {}", path, e))
+	}
+
+	// Every line is either "default <weight>" or "<number> [weight]", the weight is 1 if it is omitted.
+	// Empty lines and lines started with '#' are ignored
+	pub fn read<R: std::io::Read>(f: R, g: &GameParams) -> Result<Self, String> {
+		let mut res = Self::default();
+		let parse_weight = |s: &str| match s.parse::<f64>() {
+			Ok(w) if w.is_finite() && w >= 0.0 => Ok(w),
+			_ => Err(format!("invalid weight {}", s)),
+		};
+
+		for line in std::io::BufReader::new(f).lines() {
+			let line = line.map_err(|e| e.to_string())?;
+			let line = line.trim();
+			if line.is_empty() || line.starts_with('#') {
+				continue;
+			}
+			let items: Vec<_> = line.split_whitespace().collect();
+			match items[..] {
+				["default", w] => res.default_weight = parse_weight(w)?,
+				[num, ref w @ ..] if w.len() <= 1 => {
+					let num = g
+						.to_number_checked(num)
+						.ok_or(format!("{} isn't a valid number", num))?;
+					let w = match w {
+						[w] => parse_weight(w)?,
+						_ => 1.0,
+					};
+					*res.weights.entry(num).or_insert(0.0) += w;
+				}
+				_ => return Err(format!("unexpected line \"{}\"", line)),
+			}
+		}
+		if res.default_weight == 0.0 && res.weights.values().all(|w| *w == 0.0) {
+			return Err("all weights are zero".to_owned());
+		}
+		Ok(res)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_read() {
+		let g = GameParams::new(4);
+		let s = "# recorded games\n1234\n5678 3\n1234\n\ndefault 0.5\n";
+		let p = Prior::read(s.as_bytes(), &g).unwrap();
+		assert!(!p.is_uniform());
+		assert_eq!(p.weight(&Number::from("1234")), 2.5);
+		assert_eq!(p.weight(&Number::from("5678")), 3.5);
+		assert_eq!(p.weight(&Number::from("0123")), 0.5);
+
+		assert!(Prior::read("1123".as_bytes(), &g).is_err());
+		assert!(Prior::read("1234 -1".as_bytes(), &g).is_err());
+		assert!(Prior::read("1234 1 2".as_bytes(), &g).is_err());
+		assert!(Prior::read("default 0".as_bytes(), &g).is_err());
+	}
+
+	#[test]
+	fn test_normalized_weights() {
+		let g = GameParams::new(4);
+		let p = Prior::read("default 1\n1234 2".as_bytes(), &g).unwrap();
+		let numbers = [Number::from("1234"), Number::from("5678")];
+		assert_eq!(p.normalized_weights(&numbers), [1.5, 0.5]);
+
+		let p = Prior::default();
+		assert_eq!(p.normalized_weights(&numbers), [1.0, 1.0]);
+		assert_eq!(p.fingerprint(), "uniform");
+
+		// The listed numbers all have zero weight, so the weights fall back to uniform
+		let p = Prior::read("default 0\n0123 2".as_bytes(), &g).unwrap();
+		assert_eq!(p.normalized_weights(&numbers), [1.0, 1.0]);
+		assert_eq!(p.normalized_weights(&[]), []);
+
+		// Zero weights and the default weight alone don't make the distribution non-uniform
+		for s in ["1234 0", "default 2", "default 2\n1234 0\n"] {
+			let p = Prior::read(s.as_bytes(), &g).unwrap();
+			assert!(p.is_uniform());
+			assert_eq!(p.fingerprint(), "uniform");
+		}
+	}
+}
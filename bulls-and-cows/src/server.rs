@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::args::{get_arg_value, get_game_params};
+use crate::game_utils::{GameParams, Number};
+use crate::prior::Prior;
+use crate::random::Random;
+use crate::solver::{Solver, COUNT_LIMIT};
+use crate::strategy::{create_strategy, Strategy, StrategyType};
+
+// Count of candidates in the reply if the limit isn't given
+const DEFAULT_CANDIDATES_LIMIT: usize = 20;
+
+// Games of the sessions are smaller than the strategies support: every session keeps its own copy
+// of the strategy, and the clients aren't trusted
+const MAX_SESSION_INDEX_SPACE: u64 = 1 << 17;
+
+enum Session {
+	// The engine guesses the number of the client
+	Guessing {
+		game: GameParams,
+		strategy: Box<dyn Strategy>,
+		history: Vec<(Number, u8, u8)>,
+		// The guess that waits for the response
+		last_guess: Option<Number>,
+	},
+	// The client guesses the number hidden by the engine
+	Hosting {
+		game: GameParams,
+		secret: Number,
+		attempts: u32,
+	},
+}
+
+// Server of the line-based protocol. Every command is one line, the reply is one line too,
+// it is "OK [result]" or "ERR <message>". Commands:
+//   new <strategy> [game parameters]  start a game where the engine guesses, the result is the session id
+//   host [game parameters] [--seed <n>]  start a game where the engine hides a number, the result is the session id
+//   guess <id>  the next guess of the engine
+//   respond <id> <bulls> <cows>  response to the last guess of the engine
+//   candidates <id> [limit]  count of numbers consistent with the responses and the first of them
+//   check <id> <number>  bulls and cows of the number for the hidden one and the count of checks made
+//   close <id>  end the game
+//   quit  close the connection
+// Game parameters are the same as on the command line, e.g. "--len 5 --base 16".
+// A session is dropped when the connection that started it closes.
+// Meanwhile any client that knows the id can continue the game
+#[derive(Default)]
+pub struct Server {
+	sessions: Mutex<HashMap<u64, Arc<Mutex<Session>>>>,
+	next_id: AtomicU64,
+	// Strategies by the game and the strategy type, built on the first use.
+	// Building a strategy takes much longer than cloning it, so sessions get clones of them
+	prototypes: Mutex<HashMap<String, Box<dyn Strategy>>>,
+}
+
+impl Server {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	// Reply to the command line, without the line break. The ids of the started sessions are added to opened
+	pub fn handle_command(&self, line: &str, opened: &mut Vec<u64>) -> String {
+		let words: Vec<_> = line.split_whitespace().collect();
+		match self.execute(&words, opened) {
+			Ok(res) if res.is_empty() => "OK".to_owned(),
+			Ok(res) => format!("OK {}", res),
+			Err(err) => format!("ERR {}", err),
+		}
+	}
+
+	// Handle commands until the end of input or the quit command, then drop the sessions started here
+	pub fn serve(&self, input: impl BufRead, output: impl Write) -> std::io::Result<()> {
+		let mut opened = Vec::new();
+		let res = self.serve_lines(input, output, &mut opened);
+		let mut sessions = self.sessions.lock().unwrap();
+		for id in opened {
+			sessions.remove(&id);
+		}
+		res
+	}
+
+	fn serve_lines(
+		&self,
+		input: impl BufRead,
+		mut output: impl Write,
+		opened: &mut Vec<u64>,
+	) -> std::io::Result<()> {
+		for line in input.lines() {
+			let line = line?;
+			if line.trim() == "quit" {
+				break;
+			}
+			writeln!(output, "{}", self.handle_command(&line, opened))?;
+			output.flush()?;
+		}
+		Ok(())
+	}
+
+	// Serve every connection in its own thread. A failed connection doesn't stop the server
+	pub fn listen(self: Arc<Self>, listener: TcpListener) -> std::io::Result<()> {
+		for stream in listener.incoming() {
+			let stream = match stream {
+				Ok(stream) => stream,
+				Err(err) => {
+					eprintln!("Failed to accept a connection: {}", err);
+					continue;
+				}
+			};
+			let server = self.clone();
+			std::thread::spawn(move || {
+				let res = stream
+					.try_clone()
+					.and_then(|input| server.serve(BufReader::new(input), stream));
+				if let Err(err) = res {
+					eprintln!("Connection failed: {}", err);
+				}
+			});
+		}
+		Ok(())
+	}
+
+	fn execute(&self, words: &[&str], opened: &mut Vec<u64>) -> Result<String, String> {
+		let args: Vec<_> = words.iter().map(|x| x.to_string()).collect();
+		match words {
+			["new", strategy, ..] => {
+				let st = StrategyType::from_name(strategy)
+					.ok_or(format!("unknown strategy {}", strategy))?;
+				let game = session_game(&args)?;
+				let mut strategy = self.strategy(st, &game)?;
+				strategy.init();
+				let id = self.add_session(Session::Guessing {
+					game,
+					strategy,
+					history: Vec::new(),
+					last_guess: None,
+				});
+				opened.push(id);
+				Ok(id.to_string())
+			}
+			["host", ..] => {
+				let game = session_game(&args)?;
+				let seed = match get_arg_value(&args, "--seed") {
+					Some(v) => v.parse().map_err(|_| format!("invalid seed {}", v))?,
+					None => std::time::SystemTime::now()
+						.duration_since(std::time::UNIX_EPOCH)
+						.map_or(0, |x| x.as_nanos() as u64),
+				};
+				let secret = game
+					.random_number(&mut Random::new(seed))
+					.ok_or("there are no numbers for the game")?;
+				let id = self.add_session(Session::Hosting {
+					game,
+					secret,
+					attempts: 0,
+				});
+				opened.push(id);
+				Ok(id.to_string())
+			}
+			["close", id] => {
+				let id = parse_id(id)?;
+				opened.retain(|x| *x != id);
+				match self.sessions.lock().unwrap().remove(&id) {
+					Some(_) => Ok(String::new()),
+					None => Err(format!("unknown session {}", id)),
+				}
+			}
+			[command, id, args @ ..] => {
+				let session = self.session(parse_id(id)?)?;
+				let mut session = session.lock().unwrap();
+				execute_session_command(&mut session, command, args)
+			}
+			[] => Err("empty command".to_owned()),
+			[command] => Err(format!("unknown command {}", command)),
+		}
+	}
+
+	// A new copy of the strategy for the game. The prototypes aren't locked while a strategy is built,
+	// so sessions of other games aren't blocked
+	fn strategy(&self, st: StrategyType, game: &GameParams) -> Result<Box<dyn Strategy>, String> {
+		let key = format!("{} {:?}", game, st);
+		if let Some(s) = self.prototypes.lock().unwrap().get(&key) {
+			return Ok(s.clone_strategy());
+		}
+		let s = create_strategy(st, game, &Prior::default())?;
+		Ok(self
+			.prototypes
+			.lock()
+			.unwrap()
+			.entry(key)
+			.or_insert(s)
+			.clone_strategy())
+	}
+
+	fn add_session(&self, session: Session) -> u64 {
+		let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+		self.sessions
+			.lock()
+			.unwrap()
+			.insert(id, Arc::new(Mutex::new(session)));
+		id
+	}
+
+	// Only the session is locked while the command is executed, so other sessions aren't blocked
+	fn session(&self, id: u64) -> Result<Arc<Mutex<Session>>, String> {
+		self.sessions
+			.lock()
+			.unwrap()
+			.get(&id)
+			.cloned()
+			.ok_or(format!("unknown session {}", id))
+	}
+}
+
+fn session_game(args: &[String]) -> Result<GameParams, String> {
+	let game = get_game_params(args)?;
+	match game.index_space_size() {
+		Some(size) if size <= MAX_SESSION_INDEX_SPACE => Ok(game),
+		_ => Err(format!("the game {} is too big for the server", game)),
+	}
+}
+
+fn parse_id(s: &str) -> Result<u64, String> {
+	s.parse().map_err(|_| format!("invalid session id {}", s))
+}
+
+fn parse_u8(s: &str) -> Result<u8, String> {
+	s.parse().map_err(|_| format!("invalid value {}", s))
+}
+
+fn execute_session_command(
+	session: &mut Session,
+	command: &str,
+	args: &[&str],
+) -> Result<String, String> {
+	match (session, command, args) {
+		(
+			Session::Guessing {
+				strategy,
+				last_guess,
+				..
+			},
+			"guess",
+			[],
+		) => {
+			if last_guess.is_none() {
+				*last_guess = Some(
+					strategy
+						.make_guess()
+						.ok_or("responses are inconsistent")?
+						.clone(),
+				);
+			}
+			Ok(last_guess.as_ref().unwrap().to_string())
+		}
+		(
+			Session::Guessing {
+				game,
+				strategy,
+				history,
+				last_guess,
+			},
+			"respond",
+			[bulls, cows],
+		) => {
+			let (bulls, cows) = (parse_u8(bulls)?, parse_u8(cows)?);
+			if !game.is_valid_response(bulls, cows) {
+				return Err(format!("invalid response {} {}", bulls, cows));
+			}
+			let guess = last_guess.take().ok_or("there is no guess to respond to")?;
+			strategy.respond_to_guess(bulls, cows);
+			history.push((guess, bulls, cows));
+			Ok(String::new())
+		}
+		(Session::Guessing { game, history, .. }, "candidates", args) => {
+			let limit = match args {
+				[] => DEFAULT_CANDIDATES_LIMIT,
+				[limit] => limit
+					.parse()
+					.map_err(|_| format!("invalid limit {}", limit))?,
+				_ => return Err("too many arguments".to_owned()),
+			};
+			let mut solver = Solver::new(game, history)?;
+			let count = solver.count(COUNT_LIMIT);
+			let mut res = vec![count.to_string()];
+			if limit > 0 {
+				solver.for_each(|x| {
+					res.push(x.to_string());
+					res.len() <= limit
+				});
+			}
+			Ok(res.join(" "))
+		}
+		(
+			Session::Hosting {
+				game,
+				secret,
+				attempts,
+			},
+			"check",
+			[number],
+		) => {
+			let number = game
+				.to_number_checked(&number.to_uppercase())
+				.ok_or(format!("invalid number {}", number))?;
+			*attempts += 1;
+			let (bulls, cows) = game.calc_bc(&number, secret);
+			Ok(format!("{} {} {}", bulls, cows, attempts))
+		}
+		(_, command, _) => Err(format!(
+			"command {} isn't supported by the session or has wrong arguments",
+			command
+		)),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use std::net::TcpStream;
+
+	// Play one game where the engine guesses the hidden number, return the count of guesses
+	fn play(run: &mut dyn FnMut(&str) -> String, g: &GameParams, hidden: &Number) -> usize {
+		let reply = run(&format!("new minavg --len {} --base 6", g.number_len()));
+		let id = reply.strip_prefix("OK ").unwrap().to_owned();
+		for counter in 1..=10 {
+			let reply = run(&format!("guess {}", id));
+			let guess = Number::from(reply.strip_prefix("OK ").unwrap());
+			if &guess == hidden {
+				assert_eq!(run(&format!("close {}", id)), "OK");
+				return counter;
+			}
+			let (bulls, cows) = g.calc_bc(&guess, hidden);
+			assert_eq!(run(&format!("respond {} {} {}", id, bulls, cows)), "OK");
+		}
+		panic!("{} isn't guessed", hidden);
+	}
+
+	#[test]
+	fn test_commands() {
+		let server = Server::new();
+		let g = GameParams::new(3).with_base(6);
+		let mut run = |line: &str| server.handle_command(line, &mut Vec::new());
+		assert!(play(&mut run, &g, &Number::from("350")) <= 5);
+		// The second game gets a copy of the same strategy
+		assert!(play(&mut run, &g, &Number::from("350")) <= 5);
+		assert_eq!(server.prototypes.lock().unwrap().len(), 1);
+
+		let id = run("new naive --len 3 --base 6")
+			.strip_prefix("OK ")
+			.unwrap()
+			.to_owned();
+		assert_eq!(run(&format!("candidates {} 2", id)), "OK 120 012 013");
+		assert_eq!(
+			run(&format!("respond {} 0 0", id)),
+			"ERR there is no guess to respond to"
+		);
+		assert_eq!(run(&format!("guess {}", id)), "OK 012");
+		assert_eq!(
+			run(&format!("respond {} 3 1", id)),
+			"ERR invalid response 3 1"
+		);
+		// The sum of bulls and cows doesn't overflow
+		assert_eq!(
+			run(&format!("respond {} 200 200", id)),
+			"ERR invalid response 200 200"
+		);
+		assert_eq!(run(&format!("respond {} 0 0", id)), "OK");
+		assert_eq!(run(&format!("candidates {} 1", id)), "OK 6 345");
+		assert!(run(&format!("check {} 123", id)).starts_with("ERR"));
+		assert_eq!(run("guess 1000"), "ERR unknown session 1000");
+		assert_eq!(run("new unknown"), "ERR unknown strategy unknown");
+		assert!(run("hello").starts_with("ERR"));
+		assert_eq!(
+			run("new naive --len 6"),
+			format!(
+				"ERR the game {} is too big for the server",
+				GameParams::new(6)
+			)
+		);
+		assert!(run("host --len 6 --base 16").starts_with("ERR"));
+		assert!(run("new naive --len 5").starts_with("OK"));
+
+		let id = run("host --len 3 --base 6 --seed 5")
+			.strip_prefix("OK ")
+			.unwrap()
+			.to_owned();
+		assert!(run(&format!("guess {}", id)).starts_with("ERR"));
+		let solved: Vec<_> = crate::game_utils::get_numbers_iter(&g)
+			.filter(|x| run(&format!("check {} {}", id, x)).starts_with("OK 3 0 "))
+			.collect();
+		assert_eq!(solved.len(), 1);
+		assert_eq!(
+			run(&format!("check {} 012", id)).split(' ').next_back(),
+			Some("121")
+		);
+	}
+
+	#[test]
+	fn test_serve() {
+		// The sessions of the connection are dropped when it closes, other sessions stay
+		let server = Server::new();
+		let kept = server.handle_command("host --len 3 --base 6", &mut Vec::new());
+		let input = "new naive --len 3 --base 6\nhost --len 3 --base 6\nguess 2\n";
+		let mut output = Vec::new();
+		server.serve(input.as_bytes(), &mut output).unwrap();
+		assert_eq!(String::from_utf8(output).unwrap(), "OK 2\nOK 3\nOK 012\n");
+		assert_eq!(kept, "OK 1");
+		let sessions: Vec<_> = server.sessions.lock().unwrap().keys().copied().collect();
+		assert_eq!(sessions, [1]);
+	}
+
+	#[test]
+	fn test_tcp() {
+		let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+		let addr = listener.local_addr().unwrap();
+		let server = Arc::new(Server::new());
+		std::thread::spawn(move || server.listen(listener));
+
+		// Several clients play at the same time
+		let g = GameParams::new(3).with_base(6);
+		let clients: Vec<_> = ["012", "543", "105", "231"]
+			.into_iter()
+			.map(|hidden| {
+				std::thread::spawn(move || {
+					let stream = TcpStream::connect(addr).unwrap();
+					let mut input = BufReader::new(stream.try_clone().unwrap());
+					let mut output = stream;
+					let mut run = |line: &str| {
+						writeln!(output, "{}", line).unwrap();
+						let mut reply = String::new();
+						input.read_line(&mut reply).unwrap();
+						reply.trim_end().to_owned()
+					};
+					let count = play(&mut run, &g, &Number::from(hidden));
+					writeln!(output, "quit").unwrap();
+					count
+				})
+			})
+			.collect();
+		for client in clients {
+			assert!(client.join().unwrap() <= 5);
+		}
+	}
+}
@@ -0,0 +1,322 @@
+use crate::game_utils::{GameParams, Number, MAX_BASE};
+use crate::random::Random;
+
+// Commands usually stop counting of consistent numbers here, it may take too long for big games
+pub const COUNT_LIMIT: u64 = 10_000_000;
+
+// Constraint solver that finds numbers consistent with the responses to previous guesses
+// without checking every number of the game.
+// Every position has a domain of allowed digits, the domains are narrowed by the responses
+// before the search. Then digits are set position by position and a prefix is rejected
+// as soon as the bulls or the common digits of some response can't be reached
+pub struct Solver<'a> {
+	game: &'a GameParams,
+	history: &'a [(Number, u8, u8)],
+	// Bit d of domains[pos] is set if the digit d is allowed at the position
+	domains: Vec<u64>,
+	// Count of every digit in the guesses of history
+	guess_counts: Vec<[u8; MAX_BASE as usize]>,
+	cur: Vec<u8>,
+	// Count of every digit in cur
+	counts: [u8; MAX_BASE as usize],
+	// Bulls and common digits (bulls + cows) of cur and every guess
+	scores: Vec<(u8, u8)>,
+	// Digits are tried in random order if it is set, otherwise in ascending order
+	random: Option<&'a mut Random>,
+	// None means the search isn't limited
+	steps_left: Option<usize>,
+}
+
+impl<'a> Solver<'a> {
+	// Fails if a guess has another length or a response is impossible in the game
+	pub fn new(game: &'a GameParams, history: &'a [(Number, u8, u8)]) -> Result<Self, String> {
+		let n = game.number_len() as usize;
+		for (guess, bulls, cows) in history {
+			if guess.digits().len() != n || guess.digits().iter().any(|d| *d >= MAX_BASE) {
+				return Err(format!("invalid guess {}", guess));
+			}
+			if !game.is_valid_response(*bulls, *cows) {
+				return Err(format!("invalid response {} {}", bulls, cows));
+			}
+		}
+		let alphabet = game.digits().iter().fold(0u64, |m, d| m | (1 << d));
+		let mut domains = vec![alphabet; n];
+		if !game.leading_zero() {
+			domains[0] &= !1;
+		}
+		for (guess, bulls, cows) in history {
+			let digits = guess.digits();
+			let guess_mask = digits.iter().fold(0u64, |m, d| m | (1 << d));
+			let common = bulls + cows;
+			for (pos, domain) in domains.iter_mut().enumerate() {
+				let d = digits.get(pos).map_or(0, |d| 1 << d);
+				if *bulls as usize == n {
+					*domain &= d;
+				} else if *bulls == 0 {
+					*domain &= !d;
+				}
+				// No common digits, or all digits of the number are in the guess
+				if common == 0 {
+					*domain &= !guess_mask;
+				} else if common as usize == n {
+					*domain &= guess_mask;
+				}
+			}
+		}
+
+		Ok(Self {
+			game,
+			history,
+			domains,
+			guess_counts: history
+				.iter()
+				.map(|(guess, _, _)| {
+					let mut counts = [0; MAX_BASE as usize];
+					for d in guess.digits() {
+						counts[*d as usize] += 1;
+					}
+					counts
+				})
+				.collect(),
+			cur: Vec::new(),
+			counts: [0; MAX_BASE as usize],
+			scores: vec![(0, 0); history.len()],
+			random: None,
+			steps_left: None,
+		})
+	}
+
+	pub fn with_random(mut self, random: &'a mut Random) -> Self {
+		self.random = Some(random);
+		self
+	}
+
+	// Limit the count of steps of the next searches, None removes the limit
+	pub fn set_steps_limit(&mut self, steps: Option<usize>) {
+		self.steps_left = steps;
+	}
+
+	pub fn steps_left(&self) -> Option<usize> {
+		self.steps_left
+	}
+
+	// Digits that are allowed at the position after narrowing by the responses
+	pub fn domain(&self, pos: usize) -> Vec<u8> {
+		(0..MAX_BASE)
+			.filter(|d| self.domains[pos] & (1 << d) != 0)
+			.collect()
+	}
+
+	// Call f for every consistent number while it returns true.
+	// Returns false if the search was stopped by f or by the steps limit before all numbers were found
+	pub fn for_each(&mut self, mut f: impl FnMut(&Number) -> bool) -> bool {
+		!self.run(&mut f)
+	}
+
+	pub fn first(&mut self) -> Option<Number> {
+		let mut res = None;
+		self.for_each(|x| {
+			res = Some(x.clone());
+			false
+		});
+		res
+	}
+
+	// Count of consistent numbers, the search stops when the limit is reached
+	pub fn count(&mut self, limit: u64) -> u64 {
+		let mut res = 0;
+		self.for_each(|_| {
+			res += 1;
+			res < limit
+		});
+		res
+	}
+
+	// Returns true if the search should stop
+	fn run(&mut self, f: &mut dyn FnMut(&Number) -> bool) -> bool {
+		let pos = self.cur.len();
+		let n = self.game.number_len() as usize;
+		if pos == n {
+			return !f(&Number::from_digits(self.cur.clone()));
+		}
+		if let Some(steps) = &mut self.steps_left {
+			if *steps == 0 {
+				return true;
+			}
+			*steps -= 1;
+		}
+
+		let mut digits: Vec<_> = (0..MAX_BASE)
+			.filter(|d| {
+				self.domains[pos] & (1 << d) != 0
+					&& self.counts[*d as usize] < self.game.max_repetitions()
+			})
+			.collect();
+		if let Some(random) = &mut self.random {
+			random.shuffle(&mut digits);
+		}
+		let left = (n - pos - 1) as u8;
+		for d in digits {
+			let stop = self.push(d, left) && self.run(f);
+			self.pop();
+			if stop {
+				return true;
+			}
+		}
+		false
+	}
+
+	// Add the digit, return false if the prefix can't be extended to a consistent number
+	fn push(&mut self, d: u8, left: u8) -> bool {
+		let pos = self.cur.len();
+		for (i, (guess, _, _)) in self.history.iter().enumerate() {
+			let score = &mut self.scores[i];
+			if guess.digits()[pos] == d {
+				score.0 += 1;
+			}
+			if self.counts[d as usize] < self.guess_counts[i][d as usize] {
+				score.1 += 1;
+			}
+		}
+		self.cur.push(d);
+		self.counts[d as usize] += 1;
+
+		self.history
+			.iter()
+			.zip(self.scores.iter())
+			.all(|((guess, bulls, cows), score)| {
+				// A bull is possible only at the positions where the digit of the guess
+				// is in the domain and isn't used up
+				let max_bulls = guess.digits()[pos + 1..]
+					.iter()
+					.enumerate()
+					.filter(|(i, x)| {
+						self.domains[pos + 1 + i] & (1 << **x) != 0
+							&& self.counts[**x as usize] < self.game.max_repetitions()
+					})
+					.count() as u8;
+				// Every next digit adds at most one common digit
+				let common = bulls + cows;
+				score.0 <= *bulls
+					&& score.0 + max_bulls >= *bulls
+					&& score.1 <= common
+					&& score.1 + left >= common
+			})
+	}
+
+	fn pop(&mut self) {
+		let pos = self.cur.len() - 1;
+		let d = self.cur.pop().unwrap();
+		self.counts[d as usize] -= 1;
+		for (i, (guess, _, _)) in self.history.iter().enumerate() {
+			let score = &mut self.scores[i];
+			if guess.digits()[pos] == d {
+				score.0 -= 1;
+			}
+			if self.counts[d as usize] < self.guess_counts[i][d as usize] {
+				score.1 -= 1;
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::game_utils::get_numbers_iter;
+
+	fn brute_force(g: &GameParams, history: &[(Number, u8, u8)]) -> Vec<Number> {
+		get_numbers_iter(g)
+			.filter(|x| {
+				history
+					.iter()
+					.all(|(guess, bulls, cows)| g.calc_bc(guess, x) == (*bulls, *cows))
+			})
+			.collect()
+	}
+
+	#[test]
+	fn test_same_as_brute_force() {
+		let games = [
+			GameParams::new(4),
+			GameParams::new(3).with_base(6).with_repetitions(true),
+			GameParams::new(4)
+				.with_base(7)
+				.with_max_repetitions(2)
+				.with_leading_zero(false),
+			GameParams::new(3).with_alphabet("13579B").unwrap(),
+		];
+		let mut random = Random::new(1);
+		for g in games {
+			let numbers: Vec<_> = get_numbers_iter(&g).collect();
+			for _ in 0..10 {
+				let hidden = &numbers[random.gen_range(numbers.len() as u64) as usize];
+				let mut history = Vec::new();
+				for _ in 0..3 {
+					let guess = numbers[random.gen_range(numbers.len() as u64) as usize].clone();
+					let (bulls, cows) = g.calc_bc(&guess, hidden);
+					history.push((guess, bulls, cows));
+
+					let expected = brute_force(&g, &history);
+					let mut found = Vec::new();
+					assert!(Solver::new(&g, &history).unwrap().for_each(|x| {
+						found.push(x.clone());
+						true
+					}));
+					// Without the random order numbers are found in ascending order
+					assert_eq!(found, expected);
+					let count = Solver::new(&g, &history).unwrap().count(u64::MAX);
+					assert_eq!(count, expected.len() as u64);
+
+					let mut found = Vec::new();
+					Solver::new(&g, &history)
+						.unwrap()
+						.with_random(&mut random)
+						.for_each(|x| {
+							found.push(x.clone());
+							true
+						});
+					found.sort();
+					assert_eq!(found, expected);
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_domains() {
+		let g = GameParams::new(4);
+		let history = [(Number::from("0123"), 0, 0), (Number::from("4567"), 0, 4)];
+		let s = Solver::new(&g, &history).unwrap();
+		assert_eq!(s.domain(0), vec![5, 6, 7]);
+		assert_eq!(s.domain(3), vec![4, 5, 6]);
+		assert_eq!(Solver::new(&g, &history).unwrap().count(u64::MAX), 9);
+		assert_eq!(Solver::new(&g, &history).unwrap().count(5), 5);
+
+		// Responses are inconsistent
+		let history = [(Number::from("0123"), 1, 0), (Number::from("0123"), 0, 1)];
+		assert_eq!(Solver::new(&g, &history).unwrap().first(), None);
+
+		// Impossible responses are rejected, they can't overflow
+		let history = [(Number::from("0123"), 200, 200)];
+		assert!(Solver::new(&g, &history).is_err());
+		let history = [(Number::from("0123"), 3, 2)];
+		assert!(Solver::new(&g, &history).is_err());
+		let history = [(Number::from("012"), 0, 0)];
+		assert!(Solver::new(&g, &history).is_err());
+		assert!(g.is_valid_response(2, 2));
+		assert!(!g.is_valid_response(200, 200));
+	}
+
+	#[test]
+	fn test_steps_limit() {
+		let g = GameParams::new(8).with_base(16);
+		let history = [(g.to_number_checked("01234567").unwrap(), 0, 0)];
+		let mut s = Solver::new(&g, &history).unwrap();
+		s.set_steps_limit(Some(3));
+		assert!(!s.for_each(|_| true));
+		assert_eq!(s.steps_left(), Some(0));
+		s.set_steps_limit(None);
+		assert_eq!(s.first(), g.to_number_checked("89ABCDEF"));
+	}
+}
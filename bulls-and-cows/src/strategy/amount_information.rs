@@ -1,10 +1,58 @@
 use super::TargetFunc;
 
+// Entropies are compared rounded to this step, so the rounding errors of the sums don't matter
+const EPS: f64 = 1e-9;
+
 #[derive(Clone)]
 pub struct AmountInfFunc {}
 
+// Expected amount of information (in bits) that the attempt gives,
+// and the probability that the attempt is the hidden number itself
+#[derive(Clone, Copy)]
+pub struct EntropyScore {
+	pub bits: f64,
+	pub win_probability: f64,
+}
+
+impl core::fmt::Debug for EntropyScore {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(
+			f,
+			"{:.4} bits expected, win chance {:.2}%",
+			self.bits,
+			self.win_probability * 100.0
+		)
+	}
+}
+
+impl EntropyScore {
+	// Comparing rounded values keeps the order transitive, unlike comparing the difference with EPS
+	fn rounded_bits(&self) -> i64 {
+		(self.bits / EPS).round() as i64
+	}
+}
+
+impl PartialEq for EntropyScore {
+	fn eq(&self, other: &Self) -> bool {
+		self.partial_cmp(other) == Some(std::cmp::Ordering::Equal)
+	}
+}
+
+// The strategy picks the minimal value, so the "smallest" score is the one with the most information.
+// If the amounts of information are equal, the attempt that can win immediately is preferred
+impl PartialOrd for EntropyScore {
+	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+		Some(
+			other
+				.rounded_bits()
+				.cmp(&self.rounded_bits())
+				.then(other.win_probability.total_cmp(&self.win_probability)),
+		)
+	}
+}
+
 impl TargetFunc for AmountInfFunc {
-	type EvaluationResult = f64;
+	type EvaluationResult = EntropyScore;
 
 	fn new(_: i32) -> Self {
 		Self {}
@@ -12,21 +60,119 @@ impl TargetFunc for AmountInfFunc {
 
 	fn evaluate_distribution(
 		&self,
-		distribution: &[i32],
-		current_candidates: i32,
+		distribution: &[f64],
+		current_candidates: f64,
+		win_weight: f64,
 	) -> Self::EvaluationResult {
-		let s = current_candidates as f64;
+		let s = current_candidates;
 
-		distribution
+		let bits = distribution
 			.iter()
 			.map(|x| {
-				let p = (*x as f64) / s;
-				p * f64::ln(p)
+				let p = x / s;
+				-p * f64::log2(p)
 			})
-			.sum()
+			.sum();
+
+		EntropyScore {
+			bits,
+			win_probability: win_weight / s,
+		}
 	}
 
 	fn get_initial_value(&mut self) -> Self::EvaluationResult {
-		1.0
+		EntropyScore {
+			bits: f64::NEG_INFINITY,
+			win_probability: 0.0,
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::game_utils::{GameParams, Number};
+	use crate::prior::Prior;
+	use crate::strategy::{create_strategy, StrategyType};
+
+	fn evaluate(distribution: &[f64], win_weight: f64) -> EntropyScore {
+		let total = distribution.iter().sum();
+		AmountInfFunc::new(4).evaluate_distribution(distribution, total, win_weight)
+	}
+
+	#[test]
+	fn test_uniform_distribution() {
+		// 4 equal buckets give exactly 2 bits
+		let r = evaluate(&[1.0, 1.0, 1.0, 1.0], 0.0);
+		assert!((r.bits - 2.0).abs() < EPS);
+
+		let r = evaluate(&[2.0, 2.0], 0.0);
+		assert!((r.bits - 1.0).abs() < EPS);
+
+		let r = evaluate(&[5.0], 0.0);
+		assert!(r.bits.abs() < EPS);
+	}
+
+	#[test]
+	fn test_non_uniform_distribution() {
+		// H(1/4, 3/4) = 2 - 3/4 * log2(3)
+		let r = evaluate(&[1.0, 3.0], 0.0);
+		assert!((r.bits - (2.0 - 0.75 * 3f64.log2())).abs() < EPS);
+
+		// H(1/2, 1/4, 1/8, 1/8) = 1/2 + 2/4 + 3/8 + 3/8
+		let r = evaluate(&[4.0, 2.0, 1.0, 1.0], 1.0);
+		assert!((r.bits - 1.75).abs() < EPS);
+		assert!((r.win_probability - 0.125).abs() < EPS);
+	}
+
+	#[test]
+	fn test_order() {
+		// More information is better
+		assert!(evaluate(&[1.0, 1.0, 1.0, 1.0], 0.0) < evaluate(&[2.0, 1.0, 1.0], 1.0));
+		// The same information, but the first attempt can win
+		assert!(evaluate(&[1.0, 1.0, 2.0], 1.0) < evaluate(&[1.0, 1.0, 2.0], 0.0));
+		assert!(evaluate(&[1.0, 1.0, 2.0], 0.0) == evaluate(&[2.0, 1.0, 1.0], 0.0));
+
+		let mut f = AmountInfFunc::new(4);
+		assert!(evaluate(&[4.0], 0.0) < f.get_initial_value());
+	}
+
+	#[test]
+	fn test_transitive_order() {
+		// Every neighbour is closer than EPS, but the ends aren't
+		let v: Vec<_> = (0..4)
+			.map(|i| EntropyScore {
+				bits: 1.0 + i as f64 * 0.6 * EPS,
+				win_probability: (3 - i) as f64 * 0.1,
+			})
+			.collect();
+		for a in v.iter() {
+			for b in v.iter() {
+				for c in v.iter() {
+					if a < b && b < c {
+						assert!(a < c);
+					}
+					if a == b && b == c {
+						assert!(a == c);
+					}
+				}
+			}
+		}
+	}
+
+	#[test]
+	fn test_first_guess() {
+		// With repetitions the first number 0000 is the worst guess
+		let g = GameParams::new(3).with_base(6).with_repetitions(true);
+		let mut s =
+			create_strategy(StrategyType::AmountInformation, &g, &Prior::default()).unwrap();
+		s.init();
+		let guess = s.make_guess().unwrap().clone();
+		assert_ne!(guess, Number::from("000"));
+		assert!(!g.is_unconstrained());
+		assert!(GameParams::new(4).is_unconstrained());
+		assert!(!GameParams::new(4)
+			.with_leading_zero(false)
+			.is_unconstrained());
 	}
 }
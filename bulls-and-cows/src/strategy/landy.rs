@@ -20,11 +20,13 @@ impl TargetFunc for LandyFunc {
 		}
 	}
 
-	fn evaluate_distribution(&self, distribution: &[i32], _: i32) -> Self::EvaluationResult {
-		distribution
-			.iter()
-			.map(|x| self.inv_values[*x as usize] * (*x as f64))
-			.sum()
+	fn evaluate_distribution(
+		&self,
+		distribution: &[f64],
+		_: f64,
+		_: f64,
+	) -> Self::EvaluationResult {
+		distribution.iter().map(|x| self.inv(*x) * x).sum()
 	}
 
 	fn get_initial_value(&mut self) -> Self::EvaluationResult {
@@ -32,6 +34,18 @@ impl TargetFunc for LandyFunc {
 	}
 }
 
+impl LandyFunc {
+	// Buckets are weighted, so their sizes aren't always integers. Use linear interpolation between the precalculated values
+	fn inv(&self, x: f64) -> f64 {
+		let i = x.floor() as usize;
+		if i + 1 >= self.inv_values.len() {
+			return calc_inv(x);
+		}
+		let t = x - i as f64;
+		self.inv_values[i] * (1.0 - t) + self.inv_values[i + 1] * t
+	}
+}
+
 // Inversion of the function x^x. The answer is calculated by binary search
 fn calc_inv(n: f64) -> f64 {
 	let mut x = 1.0;
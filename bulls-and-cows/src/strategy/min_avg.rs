@@ -4,23 +4,22 @@ use super::TargetFunc;
 pub struct MinAvgFunc {}
 
 impl TargetFunc for MinAvgFunc {
-	type EvaluationResult = u64;
+	type EvaluationResult = f64;
 
 	fn new(_: i32) -> Self {
 		Self {}
 	}
 
-	fn evaluate_distribution(&self, distribution: &[i32], _: i32) -> Self::EvaluationResult {
-		distribution
-			.iter()
-			.map(|x| {
-				let x = *x as u64;
-				x * x
-			})
-			.sum()
+	fn evaluate_distribution(
+		&self,
+		distribution: &[f64],
+		_: f64,
+		_: f64,
+	) -> Self::EvaluationResult {
+		distribution.iter().map(|x| x * x).sum()
 	}
 
 	fn get_initial_value(&mut self) -> Self::EvaluationResult {
-		u64::max_value()
+		f64::MAX
 	}
 }
@@ -4,20 +4,24 @@ use super::TargetFunc;
 pub struct MinMaxFunc {}
 
 impl TargetFunc for MinMaxFunc {
-	type EvaluationResult = Vec<i32>;
+	type EvaluationResult = Vec<f64>;
 
 	fn new(_: i32) -> Self {
 		Self {}
 	}
 
-	fn evaluate_distribution(&self, distribution: &[i32], _: i32) -> Self::EvaluationResult {
-		let mut res: Vec<_> = distribution.iter().map(|x| *x).collect();
-		res.sort();
-		res.reverse();
+	fn evaluate_distribution(
+		&self,
+		distribution: &[f64],
+		_: f64,
+		_: f64,
+	) -> Self::EvaluationResult {
+		let mut res: Vec<_> = distribution.to_vec();
+		res.sort_by(|a, b| b.total_cmp(a));
 		res
 	}
 
 	fn get_initial_value(&mut self) -> Self::EvaluationResult {
-		vec![i32::max_value()]
+		vec![f64::MAX]
 	}
 }
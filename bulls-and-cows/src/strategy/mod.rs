@@ -1,13 +1,35 @@
-use crate::game_utils::get_numbers_iter;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
 
 use super::game_utils;
 use super::game_utils::Number;
+use crate::bitset::Bitset;
+use crate::game_utils::{get_numbers_iter_ref, RefIter};
+use crate::prior::Prior;
+use response_table::{ResponseCodes, ResponseTable};
 
 mod amount_information;
 mod landy;
 mod min_avg;
 mod minmax;
 mod naive;
+mod opening_book;
+mod replay;
+mod response_table;
+mod sampling;
+mod win_chance;
+
+pub use opening_book::OpeningBook;
+pub use replay::analyze_transcript;
+
+// What is known about the opponent in the two-player game, where both players guess simultaneously
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OpponentProgress {
+	// Count of the guesses the opponent made without guessing the number
+	Guesses(u32),
+	// Count of numbers consistent with the responses the opponent got
+	Candidates(u64),
+}
 
 pub trait Strategy: Send {
 	// Init the strategy. After this call the object is ready to start a new game
@@ -18,38 +40,104 @@ pub trait Strategy: Send {
 
 	fn respond_to_guess(&mut self, bulls: u8, cows: u8);
 
+	// Tell the strategy how far the opponent is in the two-player game. Most strategies ignore it
+	fn set_opponent_progress(&mut self, _progress: OpponentProgress) {}
+
+	// Count of numbers consistent with the responses, None if the strategy doesn't keep the candidates
+	fn candidates_count(&self) -> Option<u64> {
+		None
+	}
+
+	// Describe the last guess: candidates count and the value of the target function. None if there is nothing to say
+	fn last_guess_report(&self) -> Option<String> {
+		None
+	}
+
 	fn clone_strategy(&self) -> Box<dyn Strategy>;
 }
 
 trait TargetFunc: Clone + Send {
-	type EvaluationResult;
+	type EvaluationResult: Clone + Send;
 
 	fn new(n: i32) -> Self;
 
+	// distribution contains the total weight of candidates in every non-empty bucket.
+	// Weights are scaled so the average weight of a number is 1, i.e. they are just counts if all numbers are equally likely.
+	// current_candidates is the total weight of all candidates, win_weight is the weight of the attempt if it is a candidate.
+	// Splitting a bucket or increasing win_weight must not make the result bigger,
+	// so the distribution where every candidate is in its own bucket is the best one
 	fn evaluate_distribution(
 		&self,
-		distribution: &[i32],
-		current_candidates: i32,
+		distribution: &[f64],
+		current_candidates: f64,
+		win_weight: f64,
 	) -> Self::EvaluationResult;
 
 	// The value of the type EvaluationResult which is bigger than any of returned by evaluate_distribution
 	fn get_initial_value(&mut self) -> Self::EvaluationResult;
 }
 
+// Guesses (by their indexes) and the responses to them
+type History = Vec<(usize, u8, u8)>;
+
+// Positions remembered by Decisions. The search for the rest is repeated every time
+const MAX_DECISIONS: usize = 1 << 16;
+
+// Guesses with their evaluations found for positions of the game, up to MAX_DECISIONS of them.
+// Clones of a strategy share them and don't repeat the search for the same position
+#[derive(Clone)]
+struct Decisions<K, T>(Arc<Mutex<HashMap<K, (usize, T)>>>);
+
+impl<K: Eq + std::hash::Hash, T: Clone> Decisions<K, T> {
+	fn new() -> Self {
+		Self(Arc::new(Mutex::new(HashMap::new())))
+	}
+
+	// The search runs without the lock, so clones in other threads aren't blocked
+	fn get_or_find(&self, key: K, find: impl FnOnce() -> (usize, T)) -> (usize, T) {
+		if let Some(x) = self.0.lock().unwrap().get(&key) {
+			return x.clone();
+		}
+		let res = find();
+		let mut map = self.0.lock().unwrap();
+		if map.len() < MAX_DECISIONS {
+			map.insert(key, res.clone());
+		}
+		res
+	}
+}
+
 // The basic code that can be used in almost every strategy
 // It builds a list of all possible candidates, evaluates each candidate,
 // picks the one that minimizes the target function F
-// and removes candidates that don't satisfy the condition
+// and removes candidates that don't satisfy the condition.
+// Numbers are referred by their indexes (see GameParams::rank), candidates are stored as a bitset
 #[derive(Clone)]
 struct BasicStrategy<F: TargetFunc>
 where
 	F::EvaluationResult: PartialOrd,
 {
-	all_values: Vec<Number>,
-	candidates: Vec<Number>,
+	// Numbers by their indexes. Indexes of numbers that violate the constraints contain None
+	all_values: Arc<Vec<Option<Number>>>,
+	all_weights: Arc<Vec<f64>>,
+	// Numbers that can be hidden, i.e. valid numbers with non-zero weight
+	initial_candidates: Bitset,
+	candidates: Bitset,
+	candidates_weight: f64,
+	uniform_prior: bool,
 	is_first: bool,
-	last_guess: Number,
-	n: u8,
+	last_guess: usize,
+	last_evaluation: Option<F::EvaluationResult>,
+	// Total weight of candidates before and after the last response, used to calculate the information we got
+	last_reduction: Option<(f64, f64)>,
+	history: History,
+	// Guesses found for the histories
+	decisions: Decisions<History, F::EvaluationResult>,
+	opening_book: Option<Arc<OpeningBook>>,
+	game: game_utils::GameParams,
+	codes: ResponseCodes,
+	// None if the game is too big for the table
+	table: Option<Arc<ResponseTable>>,
 	func: F,
 }
 
@@ -57,31 +145,193 @@ impl<F: TargetFunc> BasicStrategy<F>
 where
 	F::EvaluationResult: PartialOrd,
 {
-	fn new(g: &game_utils::GameParams) -> BasicStrategy<F> {
-		let all_values: Vec<_> = get_numbers_iter(&g).collect();
+	fn new(
+		g: &game_utils::GameParams,
+		prior: &Prior,
+		opening_book: Option<Arc<OpeningBook>>,
+	) -> BasicStrategy<F> {
+		let size = g.index_space_size().unwrap() as usize;
+		let mut all_values = vec![None; size];
+		let mut it = get_numbers_iter_ref(g);
+		while let Some(x) = it.next() {
+			all_values[it.index() as usize] = Some(x.clone());
+		}
+
+		let valid: Vec<_> = all_values.iter().flatten().cloned().collect();
+		let mut valid_weights = prior.normalized_weights(&valid).into_iter();
+		let all_weights: Vec<_> = all_values
+			.iter()
+			.map(|x| match x {
+				Some(_) => valid_weights.next().unwrap(),
+				None => 0.0,
+			})
+			.collect();
+
+		let mut initial_candidates = Bitset::new(size);
+		for (i, w) in all_weights.iter().enumerate() {
+			// Numbers with zero weight are never picked as hidden
+			if *w > 0.0 {
+				initial_candidates.insert(i);
+			}
+		}
+
 		let n = g.number_len();
+		let codes = ResponseCodes::new(n);
+		let table = ResponseTable::new(&all_values, &codes, |a, b| g.calc_bc(a, b)).map(Arc::new);
 		BasicStrategy {
-			all_values,
-			candidates: Vec::new(),
+			all_values: Arc::new(all_values),
+			all_weights: Arc::new(all_weights),
+			initial_candidates,
+			candidates: Bitset::default(),
+			candidates_weight: 0.0,
+			uniform_prior: prior.is_uniform(),
 			is_first: false,
-			last_guess: Number::default(),
-			n: n + 1,
+			last_guess: 0,
+			last_evaluation: None,
+			last_reduction: None,
+			history: Vec::new(),
+			decisions: Decisions::new(),
+			opening_book,
+			game: *g,
+			codes,
+			table,
 			func: F::new(n as i32),
 		}
 	}
 
-	fn evaluate_attempt(&self, attempt: &Number) -> F::EvaluationResult {
-		let mut v = [0; 25];
-		for ans in self.candidates.iter() {
-			let bc = game_utils::calc_bc_with_base(&attempt, &ans, 10);
-			v[(bc.0 * self.n + bc.1) as usize] += 1;
+	// The same position evaluated by another target function. Tables of numbers are shared
+	fn with_func<G: TargetFunc>(&self, func: G) -> BasicStrategy<G>
+	where
+		G::EvaluationResult: PartialOrd,
+	{
+		BasicStrategy {
+			all_values: self.all_values.clone(),
+			all_weights: self.all_weights.clone(),
+			initial_candidates: self.initial_candidates.clone(),
+			candidates: self.candidates.clone(),
+			candidates_weight: self.candidates_weight,
+			uniform_prior: self.uniform_prior,
+			is_first: self.is_first,
+			last_guess: self.last_guess,
+			last_evaluation: None,
+			last_reduction: self.last_reduction,
+			history: self.history.clone(),
+			decisions: Decisions::new(),
+			opening_book: None,
+			game: self.game,
+			codes: self.codes.clone(),
+			table: self.table.clone(),
+			func,
+		}
+	}
+
+	fn value(&self, idx: usize) -> &Number {
+		self.all_values[idx].as_ref().unwrap()
+	}
+
+	fn evaluate_attempt(&self, attempt: usize) -> F::EvaluationResult {
+		let words = self.candidates.nonzero_words();
+		self.evaluate_attempt_with(attempt, &words, &mut Vec::new(), &mut Vec::new())
+	}
+
+	// Same as evaluate_attempt, but takes the non-zero words of candidates
+	// and uses the given buffers instead of allocating new ones
+	fn evaluate_attempt_with(
+		&self,
+		attempt: usize,
+		words: &[(usize, u64)],
+		v: &mut Vec<f64>,
+		counts: &mut Vec<u32>,
+	) -> F::EvaluationResult {
+		v.clear();
+		v.resize(self.codes.count(), 0.0);
+		match &self.table {
+			// Counting by bit planes pays off only if words contain many candidates
+			Some(table)
+				if self.uniform_prior && self.candidates_weight >= 8.0 * words.len() as f64 =>
+			{
+				counts.clear();
+				counts.resize(self.codes.count(), 0);
+				table.count_responses(attempt, words, counts);
+				for (x, c) in v.iter_mut().zip(counts.iter()) {
+					*x = *c as f64;
+				}
+			}
+			Some(table) => table.for_each_response(attempt, words, |h, code| {
+				v[code] += self.all_weights[h];
+			}),
+			None => {
+				let a = self.value(attempt);
+				for h in self.candidates.iter() {
+					let (bulls, cows) = self.game.calc_bc(a, self.value(h));
+					v[self.codes.code(bulls, cows)] += self.all_weights[h];
+				}
+			}
 		}
-		let v: Vec<_> = v
+		let win_weight = v[self.codes.code(self.game.number_len(), 0)];
+		v.retain(|x| *x != 0.0);
+		self.func
+			.evaluate_distribution(&v[..], self.candidates_weight, win_weight)
+	}
+
+	fn update_candidates_weight(&mut self) {
+		self.candidates_weight = if self.uniform_prior {
+			self.candidates.count() as f64
+		} else {
+			self.candidates.iter().map(|i| self.all_weights[i]).sum()
+		};
+	}
+
+	// The guess from the opening book, if the book knows the current position
+	fn book_guess(&self) -> Option<usize> {
+		let book = self.opening_book.as_ref()?;
+		let guess = if self.is_first {
+			book.first_guess()
+		} else {
+			match self.history[..] {
+				[(_, bulls, cows)] => book.reply(bulls, cows)?,
+				_ => return None,
+			}
+		};
+		self.game.rank(guess).map(|x| x as usize)
+	}
+
+	// Evaluate all valid numbers and pick the best one. Candidates are checked first,
+	// so they win if some attempts are equally good
+	fn find_best_attempt(&self) -> (usize, F::EvaluationResult) {
+		let mut min_value = self.func.clone().get_initial_value();
+		let mut res = 0;
+		let words = self.candidates.nonzero_words();
+		let (mut v, mut counts) = (Vec::new(), Vec::new());
+
+		// No attempt is better than the one that separates all candidates and can win at once
+		let weights: Vec<_> = self
+			.candidates
 			.iter()
-			.filter_map(|x| if *x != 0 { Some(*x) } else { None })
+			.map(|i| self.all_weights[i])
 			.collect();
-		self.func
-			.evaluate_distribution(&v[..], self.candidates.len() as i32)
+		let max_weight = weights.iter().copied().fold(0.0, f64::max);
+		let ideal = self
+			.func
+			.evaluate_distribution(&weights, self.candidates_weight, max_weight);
+
+		let others = self
+			.all_values
+			.iter()
+			.enumerate()
+			.filter(|(i, x)| x.is_some() && !self.candidates.contains(*i))
+			.map(|(i, _)| i);
+		for attempt in self.candidates.iter().chain(others) {
+			let new_value = self.evaluate_attempt_with(attempt, &words, &mut v, &mut counts);
+			if min_value > new_value {
+				min_value = new_value;
+				res = attempt;
+				if min_value <= ideal {
+					break;
+				}
+			}
+		}
+		(res, min_value)
 	}
 }
 
@@ -91,53 +341,83 @@ where
 {
 	fn init(&mut self) {
 		self.is_first = true;
-		self.candidates = self.all_values.clone();
+		self.candidates.clone_from(&self.initial_candidates);
+		self.update_candidates_weight();
+		self.last_evaluation = None;
+		self.last_reduction = None;
+		self.history.clear();
 	}
 
 	fn make_guess(&mut self) -> Option<&Number> {
-		if self.is_first {
+		if let Some(guess) = self.book_guess() {
+			self.is_first = false;
+			self.last_guess = guess;
+			self.last_evaluation = Some(self.evaluate_attempt(guess));
+		} else if self.is_first && self.uniform_prior && self.game.is_unconstrained() {
+			// All first guesses are equally good if all numbers are equally likely and the game is symmetric
 			self.is_first = false;
-			self.last_guess = self.candidates[0].clone()
+			self.last_guess = self.candidates.first()?;
+			self.last_evaluation = Some(self.evaluate_attempt(self.last_guess));
 		} else {
-			match self.candidates.len() {
+			self.is_first = false;
+			match self.candidates.count() {
 				0 => return None,
-				1 => self.last_guess = self.candidates[0].clone(),
+				1 => {
+					self.last_guess = self.candidates.first().unwrap();
+					self.last_evaluation = Some(self.evaluate_attempt(self.last_guess));
+				}
 				_ => {
-					let mut min_value = self.func.get_initial_value();
-					let mut res = &self.all_values[0];
-
-					let mut hs = std::collections::HashSet::new();
-					for attempt in self.candidates.iter() {
-						let new_value = self.evaluate_attempt(attempt);
-						if min_value > new_value {
-							min_value = new_value;
-							res = attempt;
-						}
-						hs.insert(attempt);
-					}
-					for attempt in self.all_values.iter() {
-						if hs.contains(attempt) {
-							continue;
-						}
-						let new_value = self.evaluate_attempt(attempt);
-						if min_value > new_value {
-							min_value = new_value;
-							res = attempt;
-						}
-					}
-
-					self.last_guess = res.clone();
+					let (guess, value) = self
+						.decisions
+						.get_or_find(self.history.clone(), || self.find_best_attempt());
+					self.last_guess = guess;
+					self.last_evaluation = Some(value);
 				}
 			}
 		}
-		Some(&self.last_guess)
+		Some(self.value(self.last_guess))
 	}
 
 	fn respond_to_guess(&mut self, bulls: u8, cows: u8) {
-		self.candidates.retain(|x| {
-			let bc = game_utils::calc_bc_with_base(&self.last_guess, &x, 10);
-			bc.0 == bulls && bc.1 == cows
-		});
+		let old_weight = self.candidates_weight;
+		let attempt = self.last_guess;
+		let code = self.codes.code(bulls, cows);
+		match &self.table {
+			Some(table) => table.filter(attempt, code, &mut self.candidates),
+			None => {
+				let a = self.value(attempt);
+				let rejected: Vec<_> = self
+					.candidates
+					.iter()
+					.filter(|h| self.game.calc_bc(a, self.value(*h)) != (bulls, cows))
+					.collect();
+				for h in rejected {
+					self.candidates.remove(h);
+				}
+			}
+		}
+		self.update_candidates_weight();
+		self.last_reduction = Some((old_weight, self.candidates_weight));
+		self.history.push((attempt, bulls, cows));
+	}
+
+	fn candidates_count(&self) -> Option<u64> {
+		Some(self.candidates.count() as u64)
+	}
+
+	fn last_guess_report(&self) -> Option<String> {
+		let mut res = format!("{} candidates", self.candidates.count());
+		if let Some(e) = &self.last_evaluation {
+			res += &format!(", evaluation: {:?}", e);
+		}
+		// The information is measured by the weights, so it's the surprise of the response under the prior
+		if let Some((old_weight, new_weight)) = self.last_reduction {
+			if new_weight > 0.0 {
+				let bits = f64::log2(old_weight / new_weight);
+				res += &format!(", the previous response gave {:.4} bits", bits);
+			}
+		}
+		Some(res)
 	}
 
 	fn clone_strategy(&self) -> Box<dyn Strategy> {
@@ -162,16 +442,144 @@ pub enum StrategyType {
 
 	// Strategy that tries to minimize the average candidates count on the next step
 	MinAvg,
+
+	// Strategy for big games. It maximizes the amount of information on a random sample of candidates
+	Sampling,
+
+	// Strategy for the two-player game that maximizes the chance to guess the number before the opponent
+	WinChance,
 }
 
-pub fn create_strategy(t: StrategyType, g: &game_utils::GameParams) -> Box<dyn Strategy> {
+impl StrategyType {
+	pub fn from_name(name: &str) -> Option<Self> {
+		[
+			Self::Naive,
+			Self::AmountInformation,
+			Self::MinMax,
+			Self::Landy,
+			Self::MinAvg,
+			Self::Sampling,
+			Self::WinChance,
+		]
+		.into_iter()
+		.find(|t| format!("{:?}", t).eq_ignore_ascii_case(name))
+	}
+}
+
+// Strategies that evaluate attempts keep all numbers in memory, so they don't support bigger games
+const MAX_INDEX_SPACE_SIZE: u64 = 1 << 20;
+
+// Create the strategy. If there is a valid opening book for it, the strategy uses it
+pub fn create_strategy(
+	t: StrategyType,
+	g: &game_utils::GameParams,
+	prior: &Prior,
+) -> Result<Box<dyn Strategy>, String> {
+	check_game_size(t, g)?;
+	let book = match OpeningBook::load(t, g, prior) {
+		Ok(book) => book.map(Arc::new),
+		Err(err) => {
+			eprintln!("{}. Run the \"openings\" command to rebuild it", err);
+			None
+		}
+	};
+	Ok(create_strategy_with_book(t, g, prior, book))
+}
+
+fn check_game_size(t: StrategyType, g: &game_utils::GameParams) -> Result<(), String> {
+	match t {
+		StrategyType::Naive | StrategyType::Sampling => Ok(()),
+		_ if g
+			.index_space_size()
+			.is_some_and(|x| x <= MAX_INDEX_SPACE_SIZE) =>
+		{
+			Ok(())
+		}
+		_ => Err(format!("the game {} is too big for this strategy", g)),
+	}
+}
+
+fn create_strategy_with_book(
+	t: StrategyType,
+	g: &game_utils::GameParams,
+	prior: &Prior,
+	book: Option<Arc<OpeningBook>>,
+) -> Box<dyn Strategy> {
+	match t {
+		StrategyType::Naive => Box::new(naive::NaiveStrategy::new(*g, prior)),
+		StrategyType::AmountInformation => Box::new(BasicStrategy::<
+			amount_information::AmountInfFunc,
+		>::new(g, prior, book)),
+		StrategyType::MinMax => Box::new(BasicStrategy::<minmax::MinMaxFunc>::new(g, prior, book)),
+		StrategyType::Landy => Box::new(BasicStrategy::<landy::LandyFunc>::new(g, prior, book)),
+		StrategyType::MinAvg => Box::new(BasicStrategy::<min_avg::MinAvgFunc>::new(g, prior, book)),
+		StrategyType::Sampling => Box::new(sampling::SamplingStrategy::<
+			amount_information::AmountInfFunc,
+		>::new(*g, prior)),
+		// The choice depends on the opponent, so the opening book isn't used
+		StrategyType::WinChance => Box::new(win_chance::WinChanceStrategy::new(g, prior)),
+	}
+}
+
+// Distributions (buckets, total weight, win weight) the target functions are checked on by scoring_fingerprint
+const SCORING_PROBES: [(&[f64], f64, f64); 6] = [
+	(&[1.0, 1.0, 1.0, 1.0], 4.0, 1.0),
+	(&[2.0, 1.0, 1.0], 4.0, 0.0),
+	(&[3.0, 1.0], 4.0, 1.0),
+	(&[4.0], 4.0, 0.0),
+	(&[0.5, 1.5, 2.0, 6.0], 10.0, 0.5),
+	(&[7.0, 5.0, 3.0, 2.0, 1.0], 18.0, 1.0),
+];
+
+fn func_fingerprint<F: TargetFunc>(g: &game_utils::GameParams) -> u64
+where
+	F::EvaluationResult: core::fmt::Debug,
+{
+	let func = F::new(g.number_len() as i32);
+	let lines: Vec<_> = SCORING_PROBES
+		.iter()
+		.map(|(d, total, win)| format!("{:?}", func.evaluate_distribution(d, *total, *win)))
+		.collect();
+	game_utils::calc_checksum(&lines)
+}
+
+// Hash of the values of the target function of the strategy on fixed distributions.
+// It changes when the scoring changes, so the opening books built with the old scoring become stale
+pub fn scoring_fingerprint(t: StrategyType, g: &game_utils::GameParams) -> u64 {
 	match t {
-		StrategyType::Naive => Box::new(naive::NaiveStrategy::new(*g)),
-		StrategyType::AmountInformation => {
-			Box::new(BasicStrategy::<amount_information::AmountInfFunc>::new(g))
+		StrategyType::AmountInformation => func_fingerprint::<amount_information::AmountInfFunc>(g),
+		StrategyType::MinMax => func_fingerprint::<minmax::MinMaxFunc>(g),
+		StrategyType::Landy => func_fingerprint::<landy::LandyFunc>(g),
+		StrategyType::MinAvg => func_fingerprint::<min_avg::MinAvgFunc>(g),
+		// These strategies don't use the opening books
+		StrategyType::Naive | StrategyType::Sampling | StrategyType::WinChance => 0,
+	}
+}
+
+// Calculate the first guess and the replies to every possible response to it.
+// Ok(None) means the strategy isn't able to make the first guess
+pub fn build_opening_book(
+	t: StrategyType,
+	g: &game_utils::GameParams,
+	prior: &Prior,
+) -> Result<Option<OpeningBook>, String> {
+	check_game_size(t, g)?;
+	let mut s = create_strategy_with_book(t, g, prior, None);
+	s.init();
+	let Some(first_guess) = s.make_guess().cloned() else {
+		return Ok(None);
+	};
+	let mut book = OpeningBook::new(first_guess);
+
+	let n = g.number_len();
+	for bulls in 0..n {
+		for cows in 0..=(n - bulls) {
+			let mut s = s.clone_strategy();
+			s.respond_to_guess(bulls, cows);
+			if let Some(reply) = s.make_guess() {
+				book.add_reply(bulls, cows, reply.clone());
+			}
 		}
-		StrategyType::MinMax => Box::new(BasicStrategy::<minmax::MinMaxFunc>::new(g)),
-		StrategyType::Landy => Box::new(BasicStrategy::<landy::LandyFunc>::new(g)),
-		StrategyType::MinAvg => Box::new(BasicStrategy::<min_avg::MinAvgFunc>::new(g)),
 	}
+	Ok(Some(book))
 }
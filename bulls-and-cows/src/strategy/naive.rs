@@ -1,47 +1,115 @@
-use crate::game_utils;
+use crate::prior::Prior;
+use crate::solver::Solver;
 
 use super::game_utils::{GameParams, Number};
 use super::Strategy;
 
+// Guesses the first number that is consistent with all responses.
+// Consistent numbers are found by the solver, so the strategy works for games of any size
 #[derive(Clone)]
 pub struct NaiveStrategy {
-	all_values: Vec<Number>,
-	candidates: Vec<Number>,
+	// Numbers that are more likely than the rest, the most likely first. They are checked first
+	preferred: Vec<Number>,
+	prior: Prior,
 	game: GameParams,
+	history: Vec<(Number, u8, u8)>,
+	// Count of preferred numbers that are already rejected
+	preferred_pos: usize,
+	last_guess: Number,
 }
 
 impl NaiveStrategy {
-	pub fn new(game: GameParams) -> Self {
-		let mut all_values: Vec<_> = game_utils::get_numbers_iter(&game).collect();
-		all_values.sort();
+	pub fn new(game: GameParams, prior: &Prior) -> Self {
+		let mut preferred: Vec<_> = prior.preferred().collect();
+		preferred.sort_by(|a, b| b.1.total_cmp(&a.1));
 		NaiveStrategy {
-			all_values,
-			candidates: Vec::new(),
+			preferred: preferred.into_iter().map(|(x, _)| x.clone()).collect(),
+			prior: prior.clone(),
 			game,
+			history: Vec::new(),
+			preferred_pos: 0,
+			last_guess: Number::default(),
 		}
 	}
+
+	fn is_consistent(&self, num: &Number) -> bool {
+		self.history
+			.iter()
+			.all(|(guess, bulls, cows)| self.game.calc_bc(guess, num) == (*bulls, *cows))
+	}
 }
 
 impl Strategy for NaiveStrategy {
 	fn init(&mut self) {
-		self.candidates = self.all_values.clone();
+		self.history.clear();
+		self.preferred_pos = 0;
 	}
 
 	fn make_guess(&mut self) -> Option<&Number> {
-		self.candidates.first()
+		// Candidates only disappear, so the search continues from the last guess
+		while let Some(x) = self.preferred.get(self.preferred_pos) {
+			if self.is_consistent(x) {
+				self.last_guess = x.clone();
+				return Some(&self.last_guess);
+			}
+			self.preferred_pos += 1;
+		}
+
+		// Numbers with zero weight are never hidden
+		if self.prior.default_weight() == 0.0 {
+			return None;
+		}
+		// The rest numbers are checked in ascending order, preferred numbers are checked already
+		let prior = &self.prior;
+		let mut res = None;
+		// Impossible responses are inconsistent too
+		let Ok(mut solver) = Solver::new(&self.game, &self.history) else {
+			return None;
+		};
+		solver.for_each(|x| {
+			if prior.weight(x) == prior.default_weight() {
+				res = Some(x.clone());
+			}
+			res.is_none()
+		});
+		self.last_guess = res?;
+		Some(&self.last_guess)
 	}
 
 	fn respond_to_guess(&mut self, bulls: u8, cows: u8) {
-		if let Some(last) = self.candidates.first() {
-			let last = last.clone();
-			self.candidates.retain(|x| {
-				let bc = self.game.calc_bc(&last, &x);
-				bc.0 == bulls && bc.1 == cows
-			});
-		}
+		self.history.push((self.last_guess.clone(), bulls, cows));
 	}
 
 	fn clone_strategy(&self) -> Box<dyn Strategy> {
 		Box::new(self.clone())
 	}
 }
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn play(s: &mut NaiveStrategy, g: &GameParams, hidden: &Number) -> usize {
+		s.init();
+		for counter in 1..=30 {
+			let guess = s.make_guess().unwrap().clone();
+			if &guess == hidden {
+				return counter;
+			}
+			let (bulls, cows) = g.calc_bc(&guess, hidden);
+			s.respond_to_guess(bulls, cows);
+		}
+		panic!("{} isn't guessed", hidden);
+	}
+
+	#[test]
+	fn test_big_game() {
+		let g = GameParams::new(6).with_base(16);
+		let mut s = NaiveStrategy::new(g, &Prior::default());
+		s.init();
+		assert_eq!(s.make_guess(), Some(&Number::from("012345")));
+		// The hidden number is the last one in the order of checks
+		let hidden = g.to_number_checked("FEDCBA").unwrap();
+		assert!(play(&mut s, &g, &hidden) <= 10);
+	}
+}
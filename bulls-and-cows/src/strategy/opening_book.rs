@@ -0,0 +1,234 @@
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+use super::game_utils::{calc_checksum, GameParams, Number};
+use super::{scoring_fingerprint, StrategyType};
+use crate::prior::Prior;
+
+// The header of the book contains everything the choice of guesses depends on: the game, the strategy,
+// the prior and the scoring fingerprint, and it's covered by the checksum. Increase this value if a strategy
+// changes its choice in another way, e.g. the order of equally good attempts, so old books become stale
+const BOOK_VERSION: u32 = 2;
+
+const OPENINGS_DIR: &str = "openings";
+
+// The first guess of a strategy and its replies to every response to the first guess.
+// These guesses don't depend on the hidden number, so they can be calculated once and saved to disk
+#[derive(Debug, PartialEq)]
+pub struct OpeningBook {
+	first_guess: Number,
+	replies: BTreeMap<(u8, u8), Number>,
+}
+
+impl OpeningBook {
+	pub fn new(first_guess: Number) -> Self {
+		Self {
+			first_guess,
+			replies: BTreeMap::new(),
+		}
+	}
+
+	pub fn add_reply(&mut self, bulls: u8, cows: u8, reply: Number) {
+		self.replies.insert((bulls, cows), reply);
+	}
+
+	pub fn first_guess(&self) -> &Number {
+		&self.first_guess
+	}
+
+	pub fn reply(&self, bulls: u8, cows: u8) -> Option<&Number> {
+		self.replies.get(&(bulls, cows))
+	}
+
+	pub fn replies_count(&self) -> usize {
+		self.replies.len()
+	}
+
+	pub fn path(t: StrategyType, g: &GameParams, prior: &Prior) -> std::path::PathBuf {
+		let mut name = format!("{:?}_{}", t, g);
+		if !prior.is_uniform() {
+			name += "_";
+			name += &prior.fingerprint();
+		}
+		std::path::Path::new(OPENINGS_DIR).join(name + ".txt")
+	}
+
+	// Load the book for the given game and strategy. Ok(None) means there is no book
+	pub fn load(t: StrategyType, g: &GameParams, prior: &Prior) -> Result<Option<Self>, String> {
+		let path = Self::path(t, g, prior);
+		match std::fs::File::open(&path) {
+			Ok(f) => Self::read(f, t, g, prior)
+				.map(Some)
+				.map_err(|e| format!("Opening book {:?}: {}", path, e)),
+			Err(_) => Ok(None),
+		}
+	}
+
+	pub fn save(&self, t: StrategyType, g: &GameParams, prior: &Prior) -> std::io::Result<()> {
+		let path = Self::path(t, g, prior);
+		std::fs::create_dir_all(OPENINGS_DIR)?;
+		let mut f = std::fs::File::create(path)?;
+		self.write(&mut f, t, g, prior)
+	}
+
+	fn header(t: StrategyType, g: &GameParams, prior: &Prior) -> Vec<String> {
+		vec![
+			format!("version {}", BOOK_VERSION),
+			format!("game {}", g),
+			format!("strategy {:?}", t),
+			format!("prior {}", prior.fingerprint()),
+			format!("scoring {:016x}", scoring_fingerprint(t, g)),
+		]
+	}
+
+	fn write<W: Write>(
+		&self,
+		f: &mut W,
+		t: StrategyType,
+		g: &GameParams,
+		prior: &Prior,
+	) -> std::io::Result<()> {
+		let mut lines = Self::header(t, g, prior);
+		lines.push(format!("first {}", self.first_guess));
+		for ((b, c), reply) in self.replies.iter() {
+			lines.push(format!("reply {} {} {}", b, c, reply));
+		}
+		let checksum = calc_checksum(&lines);
+		for line in lines {
+			writeln!(f, "{}", line)?;
+		}
+		writeln!(f, "checksum {:016x}", checksum)
+	}
+
+	fn read<R: std::io::Read>(
+		f: R,
+		t: StrategyType,
+		g: &GameParams,
+		prior: &Prior,
+	) -> Result<Self, String> {
+		let mut lines = Vec::new();
+		for line in std::io::BufReader::new(f).lines() {
+			let line = line.map_err(|e| e.to_string())?;
+			let line = line.trim();
+			if !line.is_empty() {
+				lines.push(line.to_owned());
+			}
+		}
+
+		let checksum = match lines.pop() {
+			Some(s) => s
+				.strip_prefix("checksum ")
+				.and_then(|s| u64::from_str_radix(s, 16).ok())
+				.ok_or("the checksum is missing")?,
+			None => return Err("the file is empty".to_owned()),
+		};
+		if checksum != calc_checksum(&lines) {
+			return Err("checksum mismatch, the book is corrupted".to_owned());
+		}
+
+		let header = Self::header(t, g, prior);
+		if lines.len() < header.len() || lines[..header.len()] != header[..] {
+			return Err("the book is stale or was built for another game".to_owned());
+		}
+
+		let parse_number = |s: &str| {
+			g.to_number_checked(s)
+				.ok_or(format!("{} isn't a valid number", s))
+		};
+		let mut book = None;
+		for line in lines[header.len()..].iter() {
+			let items: Vec<_> = line.split(' ').collect();
+			match (items[0], &mut book) {
+				("first", None) if items.len() == 2 => {
+					book = Some(OpeningBook::new(parse_number(items[1])?));
+				}
+				("reply", Some(book)) if items.len() == 4 => {
+					let b = items[1].parse().map_err(|_| "invalid bulls count")?;
+					let c = items[2].parse().map_err(|_| "invalid cows count")?;
+					book.add_reply(b, c, parse_number(items[3])?);
+				}
+				_ => return Err(format!("unexpected line \"{}\"", line)),
+			}
+		}
+		book.ok_or("the first guess is missing".to_owned())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	fn make_book() -> OpeningBook {
+		let mut book = OpeningBook::new(Number::from("0123"));
+		book.add_reply(0, 0, Number::from("4567"));
+		book.add_reply(1, 2, Number::from("0245"));
+		book
+	}
+
+	#[test]
+	fn test_write_and_read() {
+		let g = GameParams::new(4);
+		let book = make_book();
+		let mut v = Vec::new();
+		let prior = Prior::default();
+		book.write(&mut v, StrategyType::MinAvg, &g, &prior)
+			.unwrap();
+
+		let book2 = OpeningBook::read(&v[..], StrategyType::MinAvg, &g, &prior).unwrap();
+		assert_eq!(book, book2);
+		assert_eq!(book2.reply(1, 2), Some(&Number::from("0245")));
+		assert_eq!(book2.reply(2, 1), None);
+	}
+
+	#[test]
+	fn test_stale_book() {
+		let g = GameParams::new(4);
+		let prior = Prior::default();
+		let mut v = Vec::new();
+		make_book()
+			.write(&mut v, StrategyType::MinAvg, &g, &prior)
+			.unwrap();
+
+		assert!(OpeningBook::read(&v[..], StrategyType::MinMax, &g, &prior).is_err());
+		assert!(OpeningBook::read(&v[..], StrategyType::MinAvg, &g.with_base(9), &prior).is_err());
+
+		let other_prior = Prior::read("1234 2".as_bytes(), &g).unwrap();
+		assert!(OpeningBook::read(&v[..], StrategyType::MinAvg, &g, &other_prior).is_err());
+
+		// The book of the old scoring
+		let s = String::from_utf8(v.clone()).unwrap();
+		let scoring = format!(
+			"scoring {:016x}",
+			scoring_fingerprint(StrategyType::MinAvg, &g)
+		);
+		let old: Vec<_> = s
+			.lines()
+			.map(|x| {
+				if x == scoring {
+					"scoring 0000000000000000"
+				} else {
+					x
+				}
+			})
+			.filter(|x| !x.starts_with("checksum"))
+			.map(|x| x.to_owned())
+			.collect();
+		let old = format!(
+			"{}\nchecksum {:016x}\n",
+			old.join("\n"),
+			calc_checksum(&old)
+		);
+		assert_eq!(
+			OpeningBook::read(old.as_bytes(), StrategyType::MinAvg, &g, &prior).unwrap_err(),
+			"the book is stale or was built for another game"
+		);
+		assert_ne!(
+			scoring_fingerprint(StrategyType::MinAvg, &g),
+			scoring_fingerprint(StrategyType::MinMax, &g)
+		);
+
+		// Change one of the replies
+		let s = String::from_utf8(v).unwrap().replace("4567", "4568");
+		assert!(OpeningBook::read(s.as_bytes(), StrategyType::MinAvg, &g, &prior).is_err());
+	}
+}
@@ -0,0 +1,195 @@
+use crate::game_utils::{GameParams, Number};
+use crate::prior::Prior;
+
+use super::amount_information::AmountInfFunc;
+use super::landy::LandyFunc;
+use super::min_avg::MinAvgFunc;
+use super::minmax::MinMaxFunc;
+use super::{check_game_size, BasicStrategy, Strategy, StrategyType, TargetFunc};
+
+// Moves that lose less information than this are as good as the best one
+const LOST_BITS_EPS: f64 = 1e-6;
+
+// How good the guess is by the target function of the strategy
+#[derive(Debug)]
+pub struct MoveScore {
+	pub strategy: StrategyType,
+	pub value: String,
+	// The best attempt of the strategy in the same position
+	pub best_guess: Number,
+	pub best_value: String,
+	pub is_best: bool,
+}
+
+#[derive(Debug)]
+pub struct MoveAnalysis {
+	pub guess: Number,
+	pub bulls: u8,
+	pub cows: u8,
+	// Count of candidates before and after the response
+	pub candidates: usize,
+	pub candidates_after: usize,
+	// Total weight of the candidates by the prior before and after the response
+	pub weight: f64,
+	pub weight_after: f64,
+	// The guess could be the hidden number
+	pub is_candidate: bool,
+	// Expected amount of information of the guess and of the most informative attempt
+	pub expected_bits: f64,
+	pub best_bits: f64,
+	pub scores: Vec<MoveScore>,
+}
+
+impl MoveAnalysis {
+	// Information that the response actually gave, measured by the weights like the expected one
+	pub fn gained_bits(&self) -> f64 {
+		f64::log2(self.weight / self.weight_after)
+	}
+
+	// How much less information the guess is expected to give than the best attempt
+	pub fn lost_bits(&self) -> f64 {
+		self.best_bits - self.expected_bits
+	}
+
+	pub fn lost_information(&self) -> bool {
+		self.lost_bits() > LOST_BITS_EPS
+	}
+}
+
+// Compare the guess with the best attempt by the target function F
+fn score<F: TargetFunc>(
+	s: &BasicStrategy<AmountInfFunc>,
+	strategy: StrategyType,
+	guess: usize,
+) -> MoveScore
+where
+	F::EvaluationResult: PartialOrd + core::fmt::Debug,
+{
+	let s = s.with_func(F::new(s.game.number_len() as i32));
+	let best = s.find_best_attempt();
+	score_with_best(&s, strategy, guess, best)
+}
+
+// Same as score, but the best attempt of the strategy is already found
+fn score_with_best<F: TargetFunc>(
+	s: &BasicStrategy<F>,
+	strategy: StrategyType,
+	guess: usize,
+	(best, best_value): (usize, F::EvaluationResult),
+) -> MoveScore
+where
+	F::EvaluationResult: PartialOrd + core::fmt::Debug,
+{
+	let value = s.evaluate_attempt(guess);
+	MoveScore {
+		strategy,
+		value: format!("{:?}", value),
+		best_guess: s.value(best).clone(),
+		is_best: value <= best_value,
+		best_value: format!("{:?}", best_value),
+	}
+}
+
+// Annotate every move of the finished game: the candidates that remained, the evaluations of the guess
+// by the target functions of the strategies compared to the best attempts, and the information it lost.
+// The guesses may be made by anyone, e.g. by a human. WinChance isn't used, its choice depends on the opponent
+pub fn analyze_transcript(
+	g: &GameParams,
+	prior: &Prior,
+	transcript: &[(Number, u8, u8)],
+) -> Result<Vec<MoveAnalysis>, String> {
+	check_game_size(StrategyType::MinAvg, g)?;
+	let mut s = BasicStrategy::<AmountInfFunc>::new(g, prior, None);
+	s.init();
+	let mut res = Vec::new();
+	for (i, (guess, bulls, cows)) in transcript.iter().enumerate() {
+		let idx = g
+			.rank(guess)
+			.filter(|x| s.all_values.get(*x as usize).is_some_and(Option::is_some))
+			.ok_or(format!("{} isn't a valid number of the game {}", guess, g))? as usize;
+		let candidates = s.candidates.count();
+		let is_candidate = s.candidates.contains(idx);
+		let weight = s.candidates_weight;
+		// The most informative attempt is needed both for the score and for the lost information
+		let best = s.find_best_attempt();
+		let best_bits = best.1.bits.abs();
+		let scores = vec![
+			score_with_best(&s, StrategyType::AmountInformation, idx, best),
+			score::<MinMaxFunc>(&s, StrategyType::MinMax, idx),
+			score::<LandyFunc>(&s, StrategyType::Landy, idx),
+			score::<MinAvgFunc>(&s, StrategyType::MinAvg, idx),
+		];
+		// Entropy of one bucket is -0, it shouldn't be printed with the sign
+		let expected_bits = s.evaluate_attempt(idx).bits.abs();
+
+		s.last_guess = idx;
+		s.respond_to_guess(*bulls, *cows);
+		let candidates_after = s.candidates.count();
+		if candidates_after == 0 {
+			return Err(format!(
+				"the response #{} contradicts the previous ones",
+				i + 1
+			));
+		}
+		res.push(MoveAnalysis {
+			guess: guess.clone(),
+			bulls: *bulls,
+			cows: *cows,
+			candidates,
+			candidates_after,
+			weight,
+			weight_after: s.candidates_weight,
+			is_candidate,
+			expected_bits,
+			best_bits,
+			scores,
+		});
+	}
+	Ok(res)
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_analyze_transcript() {
+		let g = GameParams::new(3).with_base(6);
+		let transcript = [
+			(Number::from("012"), 0, 1),
+			// The same guess again gives nothing
+			(Number::from("012"), 0, 1),
+			(Number::from("345"), 1, 1),
+		];
+		let res = analyze_transcript(&g, &Prior::default(), &transcript).unwrap();
+		assert_eq!(res.len(), 3);
+		assert_eq!(res[0].candidates, 120);
+		assert_eq!(res[0].candidates_after, res[1].candidates);
+		// All first guesses are the same in this game
+		assert!(!res[0].lost_information());
+		assert!(res[0].scores.iter().all(|x| x.is_best));
+
+		assert_eq!(res[1].candidates, res[1].candidates_after);
+		assert_eq!(res[1].gained_bits(), 0.0);
+		assert_eq!(res[1].expected_bits, 0.0);
+		assert!(!res[1].is_candidate);
+		assert!(res[1].lost_information());
+		assert!(res[1].scores.iter().all(|x| !x.is_best));
+		assert_eq!(res[1].scores.len(), 4);
+
+		// The information is measured by the weights: 345 is the only likely one of the 6 numbers left of 120
+		let prior = Prior::read("default 1\n345 9".as_bytes(), &g).unwrap();
+		let res = analyze_transcript(&g, &prior, &[(Number::from("012"), 0, 0)]).unwrap();
+		assert_eq!(res[0].candidates_after, 6);
+		assert!((res[0].gained_bits() - f64::log2(129.0 / 15.0)).abs() < 1e-9);
+
+		// The response can't be satisfied together with the previous ones
+		let transcript = [(Number::from("012"), 0, 0), (Number::from("345"), 0, 0)];
+		assert_eq!(
+			analyze_transcript(&g, &Prior::default(), &transcript).unwrap_err(),
+			"the response #2 contradicts the previous ones"
+		);
+		let transcript = [(Number::from("011"), 0, 0)];
+		assert!(analyze_transcript(&g, &Prior::default(), &transcript).is_err());
+	}
+}
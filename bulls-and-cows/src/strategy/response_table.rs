@@ -0,0 +1,204 @@
+use crate::bitset::Bitset;
+use crate::game_utils::Number;
+
+// Compact numbering of all possible responses (bulls, cows) for numbers of the given length
+#[derive(Clone, Debug)]
+pub struct ResponseCodes {
+	// Code of the response is codes[bulls * n + cows]
+	codes: Vec<u8>,
+	count: usize,
+	n: usize,
+}
+
+impl ResponseCodes {
+	pub fn new(number_len: u8) -> Self {
+		let n = number_len as usize + 1;
+		let mut codes = vec![u8::MAX; n * n];
+		let mut count = 0;
+		for bulls in 0..n {
+			for cows in 0..(n - bulls) {
+				codes[bulls * n + cows] = count as u8;
+				count += 1;
+			}
+		}
+		Self { codes, count, n }
+	}
+
+	pub fn count(&self) -> usize {
+		self.count
+	}
+
+	pub fn code(&self, bulls: u8, cows: u8) -> usize {
+		self.codes[bulls as usize * self.n + cows as usize] as usize
+	}
+}
+
+// Don't build tables bigger than this number of bytes
+const MAX_TABLE_SIZE: usize = 1 << 27;
+
+// Not more than 2^MAX_PLANES responses can be counted by bit planes
+const MAX_PLANES: usize = 8;
+
+// Responses for every pair (attempt, hidden number), both are indexes of numbers.
+// Bits of the response codes are stored in separate bitsets (bit planes),
+// so the candidates that give some response can be selected 64 at once
+pub struct ResponseTable {
+	planes_count: usize,
+	// Length of every plane in words
+	words: usize,
+	// The plane k of the attempt a starts at (a * planes_count + k) * words
+	planes: Vec<u64>,
+}
+
+impl ResponseTable {
+	// Numbers are indexed by their positions, None marks indexes that aren't used.
+	// None if the table is too big
+	pub fn new(
+		numbers: &[Option<Number>],
+		codes: &ResponseCodes,
+		calc_bc: impl Fn(&Number, &Number) -> (u8, u8),
+	) -> Option<Self> {
+		let planes_count = (usize::BITS - (codes.count() - 1).leading_zeros()) as usize;
+		let words = numbers.len().div_ceil(64);
+		let total_words = numbers
+			.len()
+			.checked_mul(planes_count)?
+			.checked_mul(words)?;
+		if planes_count > MAX_PLANES || total_words.checked_mul(8)? > MAX_TABLE_SIZE {
+			return None;
+		}
+
+		let mut planes = vec![0; total_words];
+		for (a, attempt) in numbers.iter().enumerate() {
+			let Some(attempt) = attempt else {
+				continue;
+			};
+			let row = &mut planes[a * planes_count * words..(a + 1) * planes_count * words];
+			for (h, hidden) in numbers.iter().enumerate() {
+				let Some(hidden) = hidden else {
+					continue;
+				};
+				let (bulls, cows) = calc_bc(attempt, hidden);
+				let code = codes.code(bulls, cows);
+				for k in 0..planes_count {
+					if code & (1 << k) != 0 {
+						row[k * words + h / 64] |= 1 << (h % 64);
+					}
+				}
+			}
+		}
+		Some(Self {
+			planes_count,
+			words,
+			planes,
+		})
+	}
+
+	fn row(&self, attempt: usize) -> &[u64] {
+		let len = self.planes_count * self.words;
+		&self.planes[attempt * len..(attempt + 1) * len]
+	}
+
+	// Call f for every candidate with the code of the response to the attempt.
+	// Candidates are given by non-zero words of their bitset, see Bitset::nonzero_words
+	pub fn for_each_response(
+		&self,
+		attempt: usize,
+		candidates: &[(usize, u64)],
+		mut f: impl FnMut(usize, usize),
+	) {
+		let row = self.row(attempt);
+		for (w, word) in candidates.iter() {
+			let (w, mut cur) = (*w, *word);
+			while cur != 0 {
+				let bit = cur.trailing_zeros();
+				cur &= cur - 1;
+				let mut code = 0;
+				for k in 0..self.planes_count {
+					code |= ((row[k * self.words + w] >> bit) as usize & 1) << k;
+				}
+				f(w * 64 + bit as usize, code);
+			}
+		}
+	}
+
+	// Add the count of candidates that give every response to the attempt
+	pub fn count_responses(&self, attempt: usize, candidates: &[(usize, u64)], counts: &mut [u32]) {
+		let row = self.row(attempt);
+		// masks[code] is the set of candidates in the current word that give the response
+		let mut masks = [0u64; 1 << MAX_PLANES];
+		for (w, word) in candidates.iter() {
+			let w = *w;
+			// Split the candidates by planes starting from the highest one,
+			// so the index of a mask becomes the code of the response
+			masks[0] = *word;
+			let mut len = 1;
+			for k in (0..self.planes_count).rev() {
+				let plane = row[k * self.words + w];
+				for i in (0..len).rev() {
+					let m = masks[i];
+					masks[2 * i] = m & !plane;
+					masks[2 * i + 1] = m & plane;
+				}
+				len *= 2;
+			}
+			for (count, m) in counts.iter_mut().zip(masks.iter()) {
+				*count += m.count_ones();
+			}
+		}
+	}
+
+	// Keep only the candidates that give the response with the given code to the attempt
+	pub fn filter(&self, attempt: usize, code: usize, candidates: &mut Bitset) {
+		let row = self.row(attempt);
+		for (w, word) in candidates.words_mut().iter_mut().enumerate() {
+			for k in 0..self.planes_count {
+				let plane = row[k * self.words + w];
+				*word &= if code & (1 << k) != 0 { plane } else { !plane };
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::game_utils::{get_numbers_iter, GameParams};
+
+	#[test]
+	fn test_table() {
+		let g = GameParams::new(3).with_base(5);
+		let numbers: Vec<_> = get_numbers_iter(&g).map(Some).collect();
+		let codes = ResponseCodes::new(3);
+		assert_eq!(codes.count(), 10);
+		let table = ResponseTable::new(&numbers, &codes, |a, b| g.calc_bc(a, b)).unwrap();
+
+		let mut candidates = Bitset::new(numbers.len());
+		for idx in (0..numbers.len()).step_by(3) {
+			candidates.insert(idx);
+		}
+		let get = |i: usize| numbers[i].as_ref().unwrap();
+		let words = candidates.nonzero_words();
+		for attempt in [0, 7, 59] {
+			let mut counts = vec![0; codes.count()];
+			table.count_responses(attempt, &words, &mut counts);
+
+			let mut expected = vec![0; codes.count()];
+			table.for_each_response(attempt, &words, |h, code| {
+				let (bulls, cows) = g.calc_bc(get(attempt), get(h));
+				assert_eq!(code, codes.code(bulls, cows));
+				expected[code] += 1;
+			});
+			assert_eq!(counts, expected);
+
+			let (bulls, cows) = g.calc_bc(get(attempt), get(3));
+			let mut filtered = candidates.clone();
+			table.filter(attempt, codes.code(bulls, cows), &mut filtered);
+			let expected: Vec<_> = candidates
+				.iter()
+				.filter(|h| g.calc_bc(get(attempt), get(*h)) == (bulls, cows))
+				.collect();
+			assert_eq!(filtered.iter().collect::<Vec<_>>(), expected);
+		}
+	}
+}
@@ -0,0 +1,244 @@
+use std::collections::HashSet;
+
+use crate::game_utils::{GameParams, Number};
+use crate::prior::Prior;
+use crate::random::Random;
+use crate::solver::Solver;
+
+use super::response_table::ResponseCodes;
+use super::{Strategy, TargetFunc};
+
+// Count of consistent numbers generated on every step
+const SAMPLE_SIZE: usize = 100;
+
+// Count of arbitrary numbers that are evaluated as attempts in addition to the sample
+const RANDOM_ATTEMPTS: usize = 50;
+
+const SEED: u64 = 0x5eed;
+
+// Search that takes longer than this count of steps is restarted with another order of digits,
+// because a different order often finds a number much faster. Every restart doubles the limit
+const SEARCH_STEPS: usize = 100;
+
+// Count of the found numbers in a row that are already in the sample, after which all numbers are enumerated
+const MAX_MISSES: usize = 10;
+
+// All numbers are enumerated if the search of one random number takes more steps
+const MAX_SAMPLING_STEPS: usize = 20000;
+
+// Strategy for games that are too big to keep all numbers in memory.
+// Every step it generates a random sample of numbers consistent with all responses,
+// evaluates them and some arbitrary numbers as attempts on this sample and picks the best one.
+// Every game starts with the same seed, so the strategy plays the same way for the same responses
+#[derive(Clone)]
+pub struct SamplingStrategy<F: TargetFunc> {
+	game: GameParams,
+	prior: Prior,
+	history: Vec<(Number, u8, u8)>,
+	last_guess: Number,
+	last_evaluation: Option<F::EvaluationResult>,
+	// Count of distinct consistent numbers found for the last guess
+	last_sample_size: usize,
+	random: Random,
+	codes: ResponseCodes,
+	func: F,
+}
+
+impl<F: TargetFunc> SamplingStrategy<F>
+where
+	F::EvaluationResult: PartialOrd,
+{
+	pub fn new(game: GameParams, prior: &Prior) -> Self {
+		let n = game.number_len();
+		Self {
+			game,
+			prior: prior.clone(),
+			history: Vec::new(),
+			last_guess: Number::default(),
+			last_evaluation: None,
+			last_sample_size: 0,
+			random: Random::new(SEED),
+			codes: ResponseCodes::new(n),
+			func: F::new(n as i32),
+		}
+	}
+
+	// Random number that is consistent with the given responses, None if there are no such numbers.
+	// The second value is the count of steps of the search
+	fn random_number(&mut self, history: &[(Number, u8, u8)]) -> (Option<Number>, usize) {
+		let Ok(solver) = Solver::new(&self.game, history) else {
+			return (None, 0);
+		};
+		let mut solver = solver.with_random(&mut self.random);
+		let mut steps = SEARCH_STEPS;
+		let mut total_steps = 0;
+		loop {
+			solver.set_steps_limit(Some(steps));
+			let mut res = None;
+			let completed = solver.for_each(|x| {
+				res = Some(x.clone());
+				false
+			});
+			total_steps += steps - solver.steps_left().unwrap();
+			if res.is_some() || completed {
+				// If the search is completed, the whole search space is checked
+				return (res, total_steps);
+			}
+			steps = steps.saturating_mul(2);
+		}
+	}
+
+	// Distinct consistent numbers with non-zero weight, at most SAMPLE_SIZE of them
+	fn sample(&mut self) -> Vec<Number> {
+		let history = std::mem::take(&mut self.history);
+		let mut res = Vec::new();
+		let mut found = HashSet::new();
+		let mut misses = 0;
+		while res.len() < SAMPLE_SIZE {
+			let (Some(x), steps) = self.random_number(&history) else {
+				break;
+			};
+			if found.insert(x.clone()) {
+				res.push(x);
+				misses = 0;
+			} else {
+				misses += 1;
+			}
+			// If the same numbers are found again and again or numbers are hard to find,
+			// probably there are a few of them. One search that doesn't stop at the first number finds all of them
+			if misses == MAX_MISSES || steps > MAX_SAMPLING_STEPS {
+				res.clear();
+				if let Ok(solver) = Solver::new(&self.game, &history) {
+					solver.with_random(&mut self.random).for_each(|x| {
+						res.push(x.clone());
+						res.len() < SAMPLE_SIZE
+					});
+				}
+				break;
+			}
+		}
+		self.history = history;
+		res.retain(|x| self.prior.weight(x) > 0.0);
+		res
+	}
+
+	fn evaluate_attempt(
+		&self,
+		attempt: &Number,
+		sample: &[(Number, f64)],
+		total: f64,
+	) -> F::EvaluationResult {
+		let mut v = vec![0.0; self.codes.count()];
+		for (x, w) in sample {
+			let (bulls, cows) = self.game.calc_bc(attempt, x);
+			v[self.codes.code(bulls, cows)] += w;
+		}
+		let win_weight = v[self.codes.code(self.game.number_len(), 0)];
+		v.retain(|x| *x != 0.0);
+		self.func.evaluate_distribution(&v, total, win_weight)
+	}
+}
+
+impl<F: TargetFunc + 'static> Strategy for SamplingStrategy<F>
+where
+	F::EvaluationResult: PartialOrd + core::fmt::Debug,
+{
+	fn init(&mut self) {
+		self.history.clear();
+		self.last_evaluation = None;
+		self.random = Random::new(SEED);
+	}
+
+	fn make_guess(&mut self) -> Option<&Number> {
+		let sample = self.sample();
+		self.last_sample_size = sample.len();
+		if sample.is_empty() {
+			return None;
+		}
+
+		// Weights are scaled like in BasicStrategy, so the average weight is 1
+		let weights: Vec<_> = sample.iter().map(|x| self.prior.weight(x)).collect();
+		let k = sample.len() as f64 / weights.iter().sum::<f64>();
+		let sample: Vec<_> = sample
+			.into_iter()
+			.zip(weights)
+			.map(|(x, w)| (x, w * k))
+			.collect();
+		let total = sample.len() as f64;
+
+		// Consistent numbers are evaluated first, so they win if attempts are equally good
+		let others: Vec<_> = (0..RANDOM_ATTEMPTS)
+			.filter_map(|_| self.random_number(&[]).0)
+			.collect();
+		let mut min_value = self.func.clone().get_initial_value();
+		let mut res = &sample[0].0;
+		for attempt in sample.iter().map(|(x, _)| x).chain(others.iter()) {
+			let new_value = self.evaluate_attempt(attempt, &sample, total);
+			if min_value > new_value {
+				min_value = new_value;
+				res = attempt;
+			}
+		}
+		self.last_guess = res.clone();
+		self.last_evaluation = Some(min_value);
+		Some(&self.last_guess)
+	}
+
+	fn respond_to_guess(&mut self, bulls: u8, cows: u8) {
+		self.history.push((self.last_guess.clone(), bulls, cows));
+	}
+
+	fn last_guess_report(&self) -> Option<String> {
+		let mut res = format!("{} sampled candidates", self.last_sample_size);
+		if let Some(e) = &self.last_evaluation {
+			res += &format!(", evaluation on the sample: {:?}", e);
+		}
+		Some(res)
+	}
+
+	fn clone_strategy(&self) -> Box<dyn Strategy> {
+		Box::new(self.clone())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::super::amount_information::AmountInfFunc;
+	use super::*;
+
+	fn play(s: &mut dyn Strategy, g: &GameParams, hidden: &Number) -> usize {
+		s.init();
+		for counter in 1..=30 {
+			let guess = s.make_guess().unwrap().clone();
+			assert!(g.is_valid(&guess));
+			if &guess == hidden {
+				return counter;
+			}
+			let (bulls, cows) = g.calc_bc(&guess, hidden);
+			s.respond_to_guess(bulls, cows);
+		}
+		panic!("{} isn't guessed", hidden);
+	}
+
+	#[test]
+	fn test_small_game() {
+		let g = GameParams::new(3).with_base(5).with_max_repetitions(2);
+		let mut s = SamplingStrategy::<AmountInfFunc>::new(g, &Prior::default());
+		for hidden in ["012", "443", "101"] {
+			assert!(play(&mut s, &g, &Number::from(hidden)) <= 8);
+		}
+
+		// Responses are inconsistent
+		s.init();
+		s.history = vec![(Number::from("012"), 1, 0), (Number::from("012"), 0, 2)];
+		assert!(s.make_guess().is_none());
+	}
+
+	#[test]
+	fn test_big_game() {
+		let g = GameParams::new(8).with_base(16).with_leading_zero(false);
+		let mut s = SamplingStrategy::<AmountInfFunc>::new(g, &Prior::default());
+		let hidden = g.to_number_checked("F0E1D2C3").unwrap();
+		assert!(play(&mut s, &g, &hidden) <= 15);
+	}
+}
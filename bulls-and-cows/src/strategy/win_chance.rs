@@ -0,0 +1,320 @@
+use std::sync::Arc;
+
+use crate::game_utils::{GameParams, Number};
+use crate::prior::Prior;
+use crate::random::Random;
+
+use super::min_avg::MinAvgFunc;
+use super::{BasicStrategy, Decisions, History, OpponentProgress, Strategy, TargetFunc};
+
+// Longest games that are taken into account
+const MAX_GUESSES: usize = 30;
+
+// Count of games that are played to collect the statistics, if the game has more numbers
+const CALIBRATION_GAMES: usize = 1000;
+
+const SEED: u64 = 0xca11b;
+
+// Bins of candidates counts are half-octaves: 1, 2, 3, 4-5, 6-7, 8-11, ...
+fn bin(count: f64) -> usize {
+	(count.max(1.0).log2() * 2.0) as usize
+}
+
+// Distribution of the count of guesses needed to finish the game by the count of candidates.
+// It's collected from the games of MinAvg strategy, which plays close to the best on average
+#[derive(Default)]
+struct FinishTable {
+	// by_candidates[bin][r - 1] is the probability to need r guesses with the count of candidates in the bin
+	by_candidates: Vec<Vec<f64>>,
+	// total[t - 1] is the probability to guess the number with t guesses
+	total: Vec<f64>,
+}
+
+impl FinishTable {
+	fn new(g: &GameParams, prior: &Prior) -> Self {
+		let mut s = BasicStrategy::<MinAvgFunc>::new(g, prior, None);
+		let mut hidden: Vec<_> = s.initial_candidates.iter().collect();
+		if hidden.len() > CALIBRATION_GAMES {
+			let mut random = Random::new(SEED);
+			random.shuffle(&mut hidden);
+			hidden.truncate(CALIBRATION_GAMES);
+		}
+
+		let bins = bin(s.initial_candidates.count() as f64) + 1;
+		let mut by_candidates = vec![vec![0.0; MAX_GUESSES]; bins];
+		let mut total = vec![0.0; MAX_GUESSES];
+		for h in hidden {
+			let weight = s.all_weights[h];
+			s.init();
+			let mut counts = Vec::new();
+			while counts.len() < MAX_GUESSES {
+				counts.push(s.candidates.count());
+				if s.make_guess().is_none() {
+					break;
+				}
+				if s.last_guess == h {
+					break;
+				}
+				let (bulls, cows) = g.calc_bc(s.value(s.last_guess), s.value(h));
+				s.respond_to_guess(bulls, cows);
+			}
+			let t = counts.len();
+			total[t - 1] += weight;
+			for (i, c) in counts.into_iter().enumerate() {
+				by_candidates[bin(c as f64)][t - i - 1] += weight;
+			}
+		}
+
+		// With one candidate the number is guessed at once
+		by_candidates[0] = vec![0.0; MAX_GUESSES];
+		by_candidates[0][0] = 1.0;
+		// More candidates can't be guessed faster. Bins without games are the same as the previous ones
+		let mut cdf: Vec<Vec<f64>> = Vec::new();
+		for (b, pmf) in by_candidates.iter().enumerate() {
+			let sum: f64 = pmf.iter().sum();
+			let mut cur = match cdf.last() {
+				Some(prev) if sum == 0.0 => prev.clone(),
+				_ => pmf
+					.iter()
+					.scan(0.0, |acc, x| {
+						*acc += x / sum;
+						Some(*acc)
+					})
+					.collect(),
+			};
+			if b > 0 {
+				for (x, prev) in cur.iter_mut().zip(cdf[b - 1].iter()) {
+					*x = f64::min(*x, *prev);
+				}
+			}
+			cdf.push(cur);
+		}
+		let by_candidates = cdf
+			.into_iter()
+			.map(|x| {
+				(0..MAX_GUESSES)
+					.map(|r| x[r] - if r > 0 { x[r - 1] } else { 0.0 })
+					.collect()
+			})
+			.collect();
+
+		let sum: f64 = total.iter().sum();
+		Self {
+			by_candidates,
+			total: total.into_iter().map(|x| x / sum).collect(),
+		}
+	}
+
+	// Probabilities to need 1, 2, ... guesses with the given count of candidates
+	fn remaining(&self, count: f64) -> &[f64] {
+		&self.by_candidates[bin(count).min(self.by_candidates.len() - 1)]
+	}
+
+	// Probabilities that the opponent guesses the number with the 1st, 2nd, ... of its next guesses
+	fn opponent(&self, progress: OpponentProgress) -> Vec<f64> {
+		match progress {
+			OpponentProgress::Candidates(count) => self.remaining(count as f64).to_vec(),
+			OpponentProgress::Guesses(guesses) => {
+				let rest = self.total.get(guesses as usize..).unwrap_or(&[]);
+				let sum: f64 = rest.iter().sum();
+				if sum == 0.0 {
+					// The opponent is slower than every game of the statistics, it may finish at any moment
+					let mut res = vec![0.0; MAX_GUESSES];
+					res[0] = 1.0;
+					return res;
+				}
+				rest.iter().map(|x| x / sum).collect()
+			}
+		}
+	}
+}
+
+// The chance not to guess the number before the opponent, a draw counts as a half of the win
+#[derive(Clone)]
+pub struct WinChanceFunc {
+	table: Arc<FinishTable>,
+	// opponent[k - 1] is the probability that the opponent guesses the number with its k-th guess from now
+	opponent: Vec<f64>,
+}
+
+impl TargetFunc for WinChanceFunc {
+	type EvaluationResult = f64;
+
+	// The table is set by WinChanceStrategy
+	fn new(_: i32) -> Self {
+		Self {
+			table: Arc::new(FinishTable::default()),
+			opponent: Vec::new(),
+		}
+	}
+
+	fn evaluate_distribution(
+		&self,
+		distribution: &[f64],
+		current_candidates: f64,
+		win_weight: f64,
+	) -> Self::EvaluationResult {
+		// finish[k - 1] is the probability to guess the number with the k-th guess from now
+		let mut finish = vec![0.0; MAX_GUESSES + 1];
+		finish[0] = win_weight / current_candidates;
+		// The bucket of the win is the attempt itself, all buckets of the same weight are the same
+		let win_bucket = distribution.iter().position(|x| *x == win_weight);
+		for (i, w) in distribution.iter().enumerate() {
+			if win_weight > 0.0 && Some(i) == win_bucket {
+				continue;
+			}
+			let p = w / current_candidates;
+			for (r, x) in self.table.remaining(*w).iter().enumerate() {
+				finish[r + 1] += p * x;
+			}
+		}
+
+		let mut res = 1.0;
+		let mut opponent_done = 0.0;
+		for (k, p) in finish.into_iter().enumerate() {
+			let opponent_now = self.opponent.get(k).copied().unwrap_or(0.0);
+			res -= p * (1.0 - opponent_done - opponent_now / 2.0);
+			opponent_done += opponent_now;
+		}
+		res
+	}
+
+	fn get_initial_value(&mut self) -> Self::EvaluationResult {
+		f64::MAX
+	}
+}
+
+// Strategy for the two-player game where both players guess simultaneously. It maximizes the chance
+// to guess the number before the opponent, so it may take risks when the opponent is close to the end
+// and play safely when it is far. Without the opponent's progress the opponent is expected
+// to make as many guesses as this strategy
+#[derive(Clone)]
+pub struct WinChanceStrategy {
+	inner: BasicStrategy<WinChanceFunc>,
+	opponent: Option<OpponentProgress>,
+	// Guesses by the histories and the distributions of the opponent's finish
+	decisions: Decisions<(History, Vec<u64>), f64>,
+}
+
+impl WinChanceStrategy {
+	pub fn new(g: &GameParams, prior: &Prior) -> Self {
+		let mut inner = BasicStrategy::<WinChanceFunc>::new(g, prior, None);
+		inner.func.table = Arc::new(FinishTable::new(g, prior));
+		Self {
+			inner,
+			opponent: None,
+			decisions: Decisions::new(),
+		}
+	}
+}
+
+impl Strategy for WinChanceStrategy {
+	fn init(&mut self) {
+		self.inner.init();
+		self.opponent = None;
+	}
+
+	fn make_guess(&mut self) -> Option<&Number> {
+		// The first guesses are equally good if all numbers are equally likely and the game is symmetric,
+		// the last candidate is the only good guess
+		let symmetric = self.inner.uniform_prior && self.inner.game.is_unconstrained();
+		if (self.inner.is_first && symmetric) || self.inner.candidates.count() <= 1 {
+			return self.inner.make_guess();
+		}
+		self.inner.is_first = false;
+
+		let progress = self
+			.opponent
+			.unwrap_or(OpponentProgress::Guesses(self.inner.history.len() as u32));
+		let opponent = self.inner.func.table.opponent(progress);
+		// Equal distributions give equal decisions
+		let key = (
+			self.inner.history.clone(),
+			opponent.iter().map(|x| x.to_bits()).collect(),
+		);
+		self.inner.func.opponent = opponent;
+		let (guess, value) = self
+			.decisions
+			.get_or_find(key, || self.inner.find_best_attempt());
+		self.inner.last_guess = guess;
+		self.inner.last_evaluation = Some(value);
+		Some(self.inner.value(guess))
+	}
+
+	fn respond_to_guess(&mut self, bulls: u8, cows: u8) {
+		self.inner.respond_to_guess(bulls, cows);
+	}
+
+	fn set_opponent_progress(&mut self, progress: OpponentProgress) {
+		self.opponent = Some(progress);
+	}
+
+	fn candidates_count(&self) -> Option<u64> {
+		self.inner.candidates_count()
+	}
+
+	fn last_guess_report(&self) -> Option<String> {
+		self.inner.last_guess_report()
+	}
+
+	fn clone_strategy(&self) -> Box<dyn Strategy> {
+		Box::new(self.clone())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_finish_table() {
+		let g = GameParams::new(3).with_base(6);
+		let table = FinishTable::new(&g, &Prior::default());
+		assert_eq!(table.remaining(1.0)[0], 1.0);
+		// MinAvg guesses one of two candidates
+		assert_eq!(table.remaining(2.0)[..2], [0.5, 0.5]);
+		let sum: f64 = table.total.iter().sum();
+		assert!((sum - 1.0).abs() < 1e-9);
+		let avg: f64 = (1..)
+			.zip(table.total.iter())
+			.map(|(t, p)| t as f64 * p)
+			.sum();
+		assert!(avg > 3.5 && avg < 4.0);
+
+		// The opponent that made many guesses finishes soon
+		let late = table.opponent(OpponentProgress::Guesses(4));
+		assert!(late[0] > table.opponent(OpponentProgress::Guesses(0))[0]);
+		assert_eq!(table.opponent(OpponentProgress::Guesses(100))[0], 1.0);
+	}
+
+	#[test]
+	fn test_risk() {
+		let g = GameParams::new(3).with_base(6);
+		let mut s = WinChanceStrategy::new(&g, &Prior::default());
+		// Three candidates: an attempt that separates them, or one of them that can win at once
+		let safe = |func: &WinChanceFunc| func.evaluate_distribution(&[1.0, 1.0, 1.0], 3.0, 0.0);
+		let risky = |func: &WinChanceFunc| func.evaluate_distribution(&[1.0, 2.0], 3.0, 1.0);
+
+		// The safe attempt guesses the number with the next guess for sure, it's enough
+		// if the opponent needs three guesses
+		s.inner.func.opponent = vec![0.0, 0.0, 1.0];
+		assert_eq!(safe(&s.inner.func), 0.0);
+		assert!(risky(&s.inner.func) > 0.0);
+		// If the opponent finishes now, only the win now gives a chance
+		s.inner.func.opponent = vec![1.0];
+		assert_eq!(safe(&s.inner.func), 1.0);
+		assert!(risky(&s.inner.func) < 1.0);
+
+		s.init();
+		s.make_guess();
+		s.respond_to_guess(0, 1);
+		s.set_opponent_progress(OpponentProgress::Candidates(1));
+		assert!(s.make_guess().is_some());
+
+		// With repetitions the first candidate 000 isn't a good first guess
+		let g = g.with_repetitions(true);
+		let mut s = WinChanceStrategy::new(&g, &Prior::default());
+		s.init();
+		assert_ne!(s.make_guess(), Some(&Number::from("000")));
+	}
+}
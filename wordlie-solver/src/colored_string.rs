@@ -2,6 +2,8 @@ use std::fmt::{Display, Formatter};
 
 use colored::Colorize;
 
+use crate::words_chooser::CharResult;
+
 #[derive(Eq, PartialEq, Clone)]
 pub enum Color {
 	Default,
@@ -26,6 +28,20 @@ impl ColoredString {
 	pub fn set_color(&mut self, idx: usize, color: Color) {
 		self.colors[idx] = color;
 	}
+
+	// The word colored by the answer to it like the tiles of the original game
+	pub fn from_answer(word: &str, answer: &[CharResult]) -> ColoredString {
+		let mut res = Self::new(word.to_owned());
+		for (i, c) in answer.iter().enumerate() {
+			let color = match c {
+				CharResult::FullMatch => Color::Green,
+				CharResult::PartialMatch => Color::Yellow,
+				CharResult::NotPresented => Color::Gray,
+			};
+			res.set_color(i, color);
+		}
+		res
+	}
 }
 
 impl Display for ColoredString {
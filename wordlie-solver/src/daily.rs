@@ -0,0 +1,215 @@
+use std::fmt::{Display, Formatter};
+
+use crate::host::HostGame;
+use crate::random::Random;
+use crate::words_chooser::CharResult;
+
+// Date of the proleptic Gregorian calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Date {
+	year: i64,
+	month: i64,
+	day: i64,
+}
+
+fn is_leap_year(year: i64) -> bool {
+	(year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+	match month {
+		2 if is_leap_year(year) => 29,
+		2 => 28,
+		4 | 6 | 9 | 11 => 30,
+		_ => 31,
+	}
+}
+
+impl Date {
+	// The date in the format YYYY-MM-DD
+	pub fn parse(s: &str) -> Result<Date, String> {
+		let err = || format!("Invalid date {}, the format is YYYY-MM-DD", s);
+		let parts: Vec<_> = s.trim().split('-').collect();
+		let [year, month, day] = parts[..] else {
+			return Err(err());
+		};
+		let parse = |x: &str| x.parse::<i64>().map_err(|_| err());
+		let (year, month, day) = (parse(year)?, parse(month)?, parse(day)?);
+		if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+			return Err(err());
+		}
+		Ok(Date { year, month, day })
+	}
+
+	// The current date in UTC
+	pub fn today() -> Date {
+		let secs = std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.map_or(0, |x| x.as_secs());
+		Date::from_days((secs / 86400) as i64)
+	}
+
+	// The date by the count of days since 1970-01-01. The calendar is counted from March,
+	// so the leap day is the last one of the year, and the years are grouped by the 400-year cycles
+	pub fn from_days(days: i64) -> Date {
+		let z = days + 719468;
+		let era = z.div_euclid(146097);
+		let doe = z.rem_euclid(146097);
+		let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+		let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+		let mp = (5 * doy + 2) / 153;
+		let day = doy - (153 * mp + 2) / 5 + 1;
+		let month = if mp < 10 { mp + 3 } else { mp - 9 };
+		let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+		Date { year, month, day }
+	}
+
+	// Count of days since 1970-01-01, the inverse of from_days
+	pub fn days(&self) -> i64 {
+		let year = if self.month <= 2 {
+			self.year - 1
+		} else {
+			self.year
+		};
+		let era = year.div_euclid(400);
+		let yoe = year.rem_euclid(400);
+		let mp = (self.month + 9) % 12;
+		let doy = (153 * mp + 2) / 5 + self.day - 1;
+		let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+		era * 146097 + doe - 719468
+	}
+}
+
+impl Display for Date {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result<(), std::fmt::Error> {
+		write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+	}
+}
+
+// The hidden word of the daily puzzle. Everyone who has the same answers and the seed gets the same word.
+// The answers are shuffled by the seed and taken one by one, so a word repeats only when all others were used
+pub fn word_of_the_day<T: AsRef<str>>(answers: &[T], date: Date, seed: u64) -> Option<String> {
+	let mut words: Vec<_> = answers.iter().map(|x| x.as_ref()).collect();
+	if words.is_empty() {
+		return None;
+	}
+	// The order of the answers in the database doesn't matter
+	words.sort();
+	words.dedup();
+	Random::new(seed).shuffle(&mut words);
+	let idx = date.days().rem_euclid(words.len() as i64) as usize;
+	Some(words[idx].to_owned())
+}
+
+fn emoji(r: CharResult) -> char {
+	match r {
+		CharResult::FullMatch => '🟩',
+		CharResult::PartialMatch => '🟨',
+		CharResult::NotPresented => '⬛',
+	}
+}
+
+// The result of the game that can be shared without spoiling the word: the title, the count of guesses
+// ("X" if the word isn't guessed, "*" marks the hard mode) and the answers as colored squares
+pub fn share_grid(title: &str, game: &HostGame) -> String {
+	let guesses = if game.is_won() {
+		game.guesses().len().to_string()
+	} else {
+		"X".to_owned()
+	};
+	let hard_mode = if game.hard_mode_info().is_some() {
+		"*"
+	} else {
+		""
+	};
+	let mut lines = vec![format!(
+		"{} {}/{}{}",
+		title,
+		guesses,
+		game.max_guesses(),
+		hard_mode
+	)];
+	lines.push(String::new());
+	for (_, answer) in game.guesses() {
+		lines.push(answer.iter().map(|x| emoji(*x)).collect());
+	}
+	lines.join("\n")
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_date() {
+		assert_eq!(Date::parse("1970-01-01").unwrap().days(), 0);
+		assert_eq!(Date::parse("2000-03-01").unwrap().days(), 11017);
+		assert_eq!(Date::parse("1969-12-31").unwrap().days(), -1);
+		assert_eq!(Date::from_days(19000).to_string(), "2022-01-08");
+		assert_eq!(Date::parse("2024-02-29").unwrap().to_string(), "2024-02-29");
+		for days in (-800000..800000).step_by(29) {
+			let date = Date::from_days(days);
+			assert_eq!(date.days(), days);
+			// The format doesn't support the years before 0
+			if date.year >= 0 {
+				assert_eq!(Date::parse(&date.to_string()), Ok(date));
+			}
+		}
+		for s in [
+			"2023-02-29",
+			"1900-02-29",
+			"2024-13-01",
+			"2024-04-31",
+			"2024-01",
+			"2024-1-x",
+		] {
+			assert!(Date::parse(s).is_err(), "{}", s);
+		}
+	}
+
+	#[test]
+	fn test_word_of_the_day() {
+		let answers = ["abcd", "abce", "bcda", "dcba", "xyzw"];
+		let date = Date::parse("2024-05-17").unwrap();
+		let word = word_of_the_day(&answers, date, 0).unwrap();
+		let mut reversed = answers;
+		reversed.reverse();
+		assert_eq!(word_of_the_day(&reversed, date, 0).unwrap(), word);
+
+		// All words are used during consecutive days
+		let mut words: Vec<_> = (0..answers.len() as i64)
+			.map(|i| word_of_the_day(&answers, Date::from_days(date.days() + i), 0).unwrap())
+			.collect();
+		words.sort();
+		assert_eq!(words, answers);
+
+		// Other seeds give other orders of the words
+		let order = |seed| {
+			(0..answers.len() as i64)
+				.map(|i| word_of_the_day(&answers, Date::from_days(i), seed).unwrap())
+				.collect::<Vec<_>>()
+		};
+		assert!((1..10).any(|seed| order(seed) != order(0)));
+		assert!(word_of_the_day::<&str>(&[], date, 0).is_none());
+	}
+
+	#[test]
+	fn test_share_grid() {
+		let answers = ["abcd", "abce", "bcda"];
+		let mut game = HostGame::new("abcd", &answers, &mut ["xyzw"].iter()).unwrap();
+		game.guess("xyzw").unwrap();
+		game.guess("bcda").unwrap();
+		game.guess("abcd").unwrap();
+		assert_eq!(
+			share_grid("Wordle 2024-05-17", &game),
+			"Wordle 2024-05-17 3/6\n\n⬛⬛⬛⬛\n🟨🟨🟨🟨\n🟩🟩🟩🟩"
+		);
+
+		let mut game = HostGame::new("abcd", &answers, &mut [].iter())
+			.unwrap()
+			.with_max_guesses(1)
+			.with_hard_mode(true);
+		game.guess("abce").unwrap();
+		assert_eq!(share_grid("Daily", &game), "Daily X/1*\n\n🟩🟩🟩⬛");
+	}
+}
@@ -1,20 +1,75 @@
+use std::collections::HashMap;
 use std::io::{BufRead, Write};
 
 pub struct WordsDb {
 	words: Vec<String>,
+	// Weights of the words that have them in the database, e.g. frequencies. Other words have equal weights
+	weights: HashMap<String, f64>,
 	db_filename: std::path::PathBuf,
 	new_words: Vec<String>,
+	// Words that can be used as guesses but are never hidden. They are read from a separate file, which isn't changed
+	allowed_guesses: Vec<String>,
 	word_current_len: usize,
 	need_flush: bool,
 }
 
+// The word and its weight if the line has it: "<word> [weight]"
+fn parse_line(line: &str) -> std::io::Result<Option<(&str, Option<f64>)>> {
+	let mut parts = line.split_whitespace();
+	let word = match parts.next() {
+		Some(word) => word,
+		None => return Ok(None),
+	};
+	let weight = match parts.next() {
+		Some(w) => match w.parse::<f64>() {
+			Ok(w) if w.is_finite() && w > 0.0 => Some(w),
+			_ => {
+				return Err(std::io::Error::new(
+					std::io::ErrorKind::InvalidData,
+					format!("Invalid weight of the word {}: {}", word, w),
+				))
+			}
+		},
+		None => None,
+	};
+	Ok(Some((word, weight)))
+}
+
+// Words of the given length with their weights, one word per line
+fn read_words<R: std::io::Read>(
+	f: R,
+	word_len: usize,
+) -> std::io::Result<Vec<(String, Option<f64>)>> {
+	let mut words = Vec::new();
+	for line in std::io::BufReader::new(f).lines() {
+		let line = line?;
+		if let Some((word, weight)) = parse_line(&line)? {
+			if word.chars().count() == word_len {
+				words.push((word.to_string(), weight));
+			}
+		}
+	}
+	Ok(words)
+}
+
+// Words of the given length from a list that the program doesn't change, their weights are ignored
+pub fn load_words(path: &std::path::Path, word_len: usize) -> std::io::Result<Vec<String>> {
+	let f = std::fs::File::open(path)?;
+	Ok(read_words(f, word_len)?
+		.into_iter()
+		.map(|(word, _)| word)
+		.collect())
+}
+
 impl WordsDb {
 	pub fn new(path: &std::path::Path, word_len: usize) -> std::io::Result<Self> {
 		match std::fs::File::open(path) {
 			Ok(mut f) => Self::new_from_file(&mut f, path, word_len),
 			Err(_) => Ok(Self {
 				words: Vec::new(),
+				weights: HashMap::new(),
 				new_words: Vec::new(),
+				allowed_guesses: Vec::new(),
 				db_filename: path.to_path_buf(),
 				word_current_len: word_len,
 				need_flush: false,
@@ -27,18 +82,16 @@ impl WordsDb {
 		path: &std::path::Path,
 		word_len: usize,
 	) -> std::io::Result<Self> {
-		let mut words = Vec::new();
-		for line in std::io::BufReader::new(f).lines() {
-			let line = line?;
-			let line = line.trim();
-			if line.chars().count() == word_len {
-				words.push(line.to_string());
-			}
-		}
+		let words = read_words(f, word_len)?;
 		Ok(Self {
-			words,
+			weights: words
+				.iter()
+				.filter_map(|(word, weight)| Some((word.clone(), (*weight)?)))
+				.collect(),
+			words: words.into_iter().map(|(word, _)| word).collect(),
 			db_filename: path.to_path_buf(),
 			new_words: Vec::new(),
+			allowed_guesses: Vec::new(),
 			word_current_len: word_len,
 			need_flush: false,
 		})
@@ -52,7 +105,7 @@ impl WordsDb {
 			.words
 			.iter()
 			.chain(self.new_words.iter())
-			.map(|x| x.clone())
+			.cloned()
 			.collect();
 		new_words.sort();
 		new_words.dedup();
@@ -74,6 +127,21 @@ impl WordsDb {
 		self.words.iter().chain(self.new_words.iter())
 	}
 
+	// Weights of the words that have them
+	pub fn weights_iter(&self) -> impl Iterator<Item = (&String, f64)> + '_ {
+		self.weights.iter().map(|(word, weight)| (word, *weight))
+	}
+
+	// The guesses aren't hidden, so their weights are ignored
+	pub fn load_allowed_guesses(&mut self, path: &std::path::Path) -> std::io::Result<()> {
+		self.allowed_guesses = load_words(path, self.word_current_len)?;
+		Ok(())
+	}
+
+	pub fn allowed_guesses_iter(&self) -> impl Iterator<Item = &String> + '_ {
+		self.allowed_guesses.iter()
+	}
+
 	pub fn flush(&mut self) -> std::io::Result<()> {
 		if !self.need_flush {
 			return Ok(());
@@ -82,36 +150,48 @@ impl WordsDb {
 			.words
 			.iter()
 			.chain(self.new_words.iter())
-			.map(|x| x.clone())
+			.cloned()
 			.collect();
 
-		let mut all_words_db = words_new.clone();
+		// Lines of the words of other lengths are kept as they are
+		let mut all_words_db: Vec<_> = words_new
+			.iter()
+			.map(|word| match self.weights.get(word) {
+				Some(weight) => format!("{} {}", word, weight),
+				None => word.clone(),
+			})
+			.collect();
 		if let Ok(f) = std::fs::File::open(&self.db_filename) {
 			for line in std::io::BufReader::new(f).lines() {
 				let line = line?;
-				let line = line.trim();
-				all_words_db.push(line.to_owned());
+				if let Ok(Some((word, _))) = parse_line(&line) {
+					if !words_new.iter().any(|x| x == word) {
+						all_words_db.push(line.trim().to_owned());
+					}
+				}
 			}
 		}
 
+		let word = |line: &String| line.split_whitespace().next().unwrap_or("").to_owned();
 		all_words_db.sort_by(|a, b| {
+			let (a, b) = (word(a), word(b));
 			let r1 = a.len().cmp(&b.len());
 			if !r1.is_eq() {
 				return r1;
 			}
-			return a.cmp(&b);
+			a.cmp(&b)
 		});
-		all_words_db.dedup();
+		all_words_db.dedup_by(|a, b| word(a) == word(b));
 		{
 			let mut f = std::fs::File::create(&self.db_filename)?;
-			f.write(all_words_db.join("\n").as_bytes())?;
+			f.write_all(all_words_db.join("\n").as_bytes())?;
 		}
 
 		self.new_words.clear();
 		self.words = words_new;
 		self.need_flush = false;
 
-		return Ok(());
+		Ok(())
 	}
 }
 
@@ -136,11 +216,10 @@ mod test {
 		fn drop(&mut self) {
 			let r = std::fs::remove_file(&self.path);
 			*self.res = r.is_err();
-			if r.is_err() {
+			if let Err(err) = r {
 				eprintln!(
 					"File delete error, filename: {:?}, err: {:?}",
-					self.path,
-					r.unwrap_err()
+					self.path, err
 				)
 			}
 		}
@@ -176,16 +255,37 @@ mod test {
 	fn test_read() {
 		let mut v: Vec<u8> = Vec::new();
 		let words = ["bar", "baz", "foo"];
-		v.write(words.join("\n").as_bytes()).unwrap();
+		v.write_all(words.join("\n").as_bytes()).unwrap();
 
 		{
 			let db = WordsDb::new_from_file(&v[..], std::path::Path::new(""), 3);
 
 			let db = db.unwrap();
 			assert_eq!(db.words, words);
+			assert!(db.allowed_guesses.is_empty());
+			assert!(db.weights.is_empty());
 		}
 	}
 
+	#[test]
+	fn test_read_words() {
+		let text = "abc\n abd \nabcd\n\nxyz 2.5";
+		assert_eq!(
+			read_words(text.as_bytes(), 3).unwrap(),
+			[
+				("abc".to_owned(), None),
+				("abd".to_owned(), None),
+				("xyz".to_owned(), Some(2.5))
+			]
+		);
+		assert_eq!(
+			read_words(text.as_bytes(), 4).unwrap(),
+			[("abcd".to_owned(), None)]
+		);
+		assert!(read_words("abc x".as_bytes(), 3).is_err());
+		assert!(read_words("abc -1".as_bytes(), 3).is_err());
+	}
+
 	#[test]
 	fn test_read_and_write() {
 		let fname = std::path::Path::new("./test_read_and_write_db");
@@ -236,6 +336,49 @@ mod test {
 		assert!(!file_delete_failed);
 	}
 
+	#[test]
+	fn test_weights() {
+		let fname = std::path::Path::new("./test_weights_db");
+		if fname.exists() {
+			panic!(
+				"The file/folder {:?} is exists before running tests!",
+				fname
+			);
+		}
+
+		let mut file_delete_failed = false;
+		{
+			let _file_deleter = FileDeleter {
+				path: fname.to_path_buf(),
+				res: &mut file_delete_failed,
+			};
+			std::fs::write(fname, "foo 3\nbar\nabcd 0.5").unwrap();
+
+			{
+				let mut db = WordsDb::new(fname, 3).unwrap();
+				assert_eq!(
+					db.weights_iter().collect::<Vec<_>>(),
+					[(&"foo".to_owned(), 3.0)]
+				);
+				db.add_word("qwe");
+			}
+			assert_eq!(
+				std::fs::read_to_string(fname).unwrap(),
+				"bar\nfoo 3\nqwe\nabcd 0.5"
+			);
+
+			{
+				let db = WordsDb::new(fname, 4).unwrap();
+				assert_eq!(db.words, ["abcd"]);
+				assert_eq!(
+					db.weights_iter().collect::<Vec<_>>(),
+					[(&"abcd".to_owned(), 0.5)]
+				);
+			}
+		}
+		assert!(!file_delete_failed);
+	}
+
 	#[test]
 	fn test_multiple_len() {
 		let fname = std::path::Path::new("./test_multiple_len_db");
@@ -339,7 +482,9 @@ mod test {
 	fn test_sync_new_words() {
 		let mut db = WordsDb {
 			words: ["abc", "bcd"].into_iter().map(|x| x.to_owned()).collect(),
+			weights: HashMap::new(),
 			new_words: Vec::new(),
+			allowed_guesses: Vec::new(),
 			db_filename: std::path::PathBuf::new(),
 			word_current_len: 3,
 			need_flush: false,
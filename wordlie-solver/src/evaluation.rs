@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::words_chooser::{calc_answer_vector, CharResult, WordsChooser};
+
+// A game that needs more guesses is considered lost
+const MAX_GUESSES: usize = 30;
+
+#[derive(Debug, Default)]
+pub struct EvaluationResult {
+	// Count of guesses for every hidden word. None if the word wasn't guessed:
+	// the chooser ran out of candidates or needed too many guesses
+	pub guesses: Vec<(String, Option<usize>)>,
+	pub time: std::time::Duration,
+}
+
+impl EvaluationResult {
+	fn won(&self) -> impl Iterator<Item = (&str, usize)> {
+		self.guesses
+			.iter()
+			.filter_map(|(word, x)| x.map(|x| (word.as_str(), x)))
+	}
+
+	// Average count of guesses in the won games, None if no game was won
+	pub fn avg(&self) -> Option<f64> {
+		let (count, total) = self
+			.won()
+			.fold((0, 0), |(count, total), (_, x)| (count + 1, total + x));
+		(count != 0).then(|| total as f64 / count as f64)
+	}
+
+	pub fn max_guesses(&self) -> usize {
+		self.won().map(|(_, x)| x).max().unwrap_or(0)
+	}
+
+	pub fn lost(&self) -> Vec<&str> {
+		self.guesses
+			.iter()
+			.filter(|(_, x)| x.is_none())
+			.map(|(word, _)| word.as_str())
+			.collect()
+	}
+
+	// histogram[k] is the count of words guessed with k guesses
+	pub fn histogram(&self) -> Vec<usize> {
+		let mut res = vec![0; self.max_guesses() + 1];
+		for (_, x) in self.won() {
+			res[x] += 1;
+		}
+		res
+	}
+
+	// Count of words that weren't guessed with the given count of guesses
+	pub fn failures(&self, max_guesses: usize) -> usize {
+		self.guesses
+			.iter()
+			.filter(|(_, x)| x.is_none_or(|x| x > max_guesses))
+			.count()
+	}
+
+	// Words that needed the most guesses, the words that weren't guessed are the first
+	pub fn worst_words(&self, count: usize) -> Vec<(&str, Option<usize>)> {
+		let mut res: Vec<_> = self
+			.guesses
+			.iter()
+			.map(|(word, x)| (word.as_str(), *x))
+			.collect();
+		res.sort_by_key(|(_, x)| std::cmp::Reverse(x.unwrap_or(usize::MAX)));
+		res.truncate(count);
+		res
+	}
+}
+
+// Attempts chosen in the positions of the games. A position is the sequence of attempts and answers,
+// and the chooser makes the same attempt in the same position
+type Positions = Mutex<HashMap<Vec<(String, Vec<CharResult>)>, Option<String>>>;
+
+// Play the game with the given hidden word, return the count of guesses. None if the word isn't guessed.
+// The attempts found in other games are taken from known, the new ones are added to it
+fn play_game(chooser: &mut WordsChooser, hidden_word: &str, known: &Positions) -> Option<usize> {
+	let mut position = Vec::new();
+	for counter in 1..=MAX_GUESSES {
+		let found = known.lock().unwrap().get(&position).cloned();
+		let attempt = match found {
+			Some(x) => x,
+			None => {
+				let x = chooser.suggest();
+				known.lock().unwrap().insert(position.clone(), x.clone());
+				x
+			}
+		}?;
+		let answer = calc_answer_vector(&attempt, hidden_word);
+		if answer.iter().all(|x| x == &CharResult::FullMatch) {
+			return Some(counter);
+		}
+		chooser.apply_answer(&attempt, &answer).ok()?;
+		position.push((attempt, answer));
+	}
+	None
+}
+
+// Play with every word as the hidden one. Every game starts with a copy of the chooser.
+// Words are split between threads, the threads share the attempts, so every position is solved once
+pub fn evaluate(chooser: &WordsChooser, hidden_words: &[String]) -> EvaluationResult {
+	let start_time = std::time::Instant::now();
+	let threads = std::thread::available_parallelism().map_or(1, |x| x.get());
+	let chunk_size = hidden_words.len().div_ceil(threads).max(1);
+	let known = Positions::default();
+	let known = &known;
+	let guesses = std::thread::scope(|s| {
+		let handles: Vec<_> = hidden_words
+			.chunks(chunk_size)
+			.map(|words| {
+				s.spawn(move || {
+					words
+						.iter()
+						.map(|word| (word.clone(), play_game(&mut chooser.clone(), word, known)))
+						.collect::<Vec<_>>()
+				})
+			})
+			.collect();
+		handles
+			.into_iter()
+			.flat_map(|h| h.join().unwrap())
+			.collect()
+	});
+	EvaluationResult {
+		guesses,
+		time: start_time.elapsed(),
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::scoring::ScoringStrategy;
+
+	#[test]
+	fn test_evaluate() {
+		let words: Vec<_> = [
+			"crane", "slate", "trace", "crate", "grace", "brace", "place", "plane", "plant",
+			"giant", "grant", "slant",
+		]
+		.iter()
+		.map(|x| x.to_string())
+		.collect();
+		let template = WordsChooser::new(&mut words.iter(), None).unwrap();
+		for scoring in ScoringStrategy::ALL {
+			let res = evaluate(&template.clone().with_scoring(scoring), &words);
+			assert_eq!(res.guesses.len(), words.len());
+			assert!(res.lost().is_empty());
+			let avg = res.avg().unwrap();
+			assert!((1.0..4.0).contains(&avg), "{:?}", scoring);
+			assert!(res.max_guesses() <= 5);
+			let histogram = res.histogram();
+			assert_eq!(histogram.iter().sum::<usize>(), words.len());
+			// Only the first guess wins at once
+			assert_eq!(histogram[1], 1);
+			assert_eq!(res.failures(6), 0);
+			assert_eq!(res.worst_words(2)[0].1, Some(res.max_guesses()));
+		}
+
+		// The word isn't in the vocabulary
+		let res = evaluate(&template, &["zzzzz".to_owned(), "crane".to_owned()]);
+		assert_eq!(res.lost(), ["zzzzz"]);
+		assert_eq!(res.failures(6), 1);
+		assert!(res.avg().is_some());
+		let res = evaluate(&template, &["zzzzz".to_owned()]);
+		assert_eq!(res.avg(), None);
+		assert_eq!(res.worst_words(1), [("zzzzz", None)]);
+	}
+}
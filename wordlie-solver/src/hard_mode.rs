@@ -0,0 +1,100 @@
+use crate::words_chooser::CharResult;
+
+// Information revealed by the answers. In the hard mode every guess should use it:
+// full matches stay in their places, and partial matches are used somewhere in the word
+#[derive(Clone, Debug, Default)]
+pub struct RevealedInfo {
+	// Letters known by full matches
+	fixed: Vec<Option<char>>,
+	// Minimal counts of letters in the hidden word, in the order they were revealed
+	min_counts: Vec<(char, usize)>,
+}
+
+impl RevealedInfo {
+	pub fn update(&mut self, attempt_word: &str, answer: &[CharResult]) {
+		let letters: Vec<_> = attempt_word.chars().collect();
+		if self.fixed.len() < letters.len() {
+			self.fixed.resize(letters.len(), None);
+		}
+		let mut counts: Vec<(char, usize)> = Vec::new();
+		for (i, (c, r)) in letters.iter().zip(answer.iter()).enumerate() {
+			if *r == CharResult::NotPresented {
+				continue;
+			}
+			if *r == CharResult::FullMatch {
+				self.fixed[i] = Some(*c);
+			}
+			match counts.iter_mut().find(|(x, _)| x == c) {
+				Some((_, n)) => *n += 1,
+				None => counts.push((*c, 1)),
+			}
+		}
+		for (c, n) in counts {
+			match self.min_counts.iter_mut().find(|(x, _)| *x == c) {
+				Some((_, m)) => *m = usize::max(*m, n),
+				None => self.min_counts.push((c, n)),
+			}
+		}
+	}
+
+	// Ok if the word can be a guess in the hard mode, otherwise the explanation why it can't
+	pub fn check(&self, word: &str) -> Result<(), String> {
+		let letters: Vec<_> = word.chars().collect();
+		for (i, c) in self.fixed.iter().enumerate() {
+			if let Some(c) = c {
+				if letters.get(i) != Some(c) {
+					return Err(format!(
+						"The letter {} should be at the position {}",
+						c,
+						i + 1
+					));
+				}
+			}
+		}
+		for (c, n) in self.min_counts.iter() {
+			if letters.iter().filter(|x| *x == c).count() < *n {
+				return Err(format!("The word should contain {} letter(s) {}", n, c));
+			}
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+	use crate::words_chooser::calc_answer_vector;
+
+	#[test]
+	fn test_revealed_info() {
+		let mut info = RevealedInfo::default();
+		assert!(info.check("abcde").is_ok());
+
+		// The second letter is a full match, other letters aren't in the word
+		info.update("eerie", &calc_answer_vector("eerie", "lemon"));
+		assert!(info.check("melon").is_ok());
+		assert!(info.check("lemon").is_ok());
+		assert_eq!(
+			info.check("axxxx").unwrap_err(),
+			"The letter e should be at the position 2"
+		);
+
+		// "l" and the first "e" are partial matches
+		info.update("sleep", &calc_answer_vector("sleep", "lemon"));
+		assert!(info.check("lemon").is_ok());
+		assert!(info.check("bemol").is_ok());
+		assert_eq!(
+			info.check("bemox").unwrap_err(),
+			"The word should contain 1 letter(s) l"
+		);
+
+		// Three letters "e" are revealed
+		let mut info = RevealedInfo::default();
+		info.update("geese", &calc_answer_vector("geese", "eerie"));
+		assert!(info.check("eerie").is_ok());
+		assert_eq!(
+			info.check("xexxe").unwrap_err(),
+			"The word should contain 3 letter(s) e"
+		);
+	}
+}
@@ -0,0 +1,167 @@
+use std::collections::HashSet;
+
+use crate::hard_mode::RevealedInfo;
+use crate::random::Random;
+use crate::words_chooser::{calc_answer_vector, CharResult};
+
+// Limit of guesses in the original game
+pub const DEFAULT_MAX_GUESSES: usize = 6;
+
+// The game where the program hides the word and the user guesses it. Answers are given by the official rule
+pub struct HostGame {
+	hidden_word: String,
+	// Words that can be used as guesses
+	vocabulary: HashSet<String>,
+	max_guesses: usize,
+	guesses: Vec<(String, Vec<CharResult>)>,
+	hard_mode: bool,
+	revealed: RevealedInfo,
+}
+
+// The random word to hide, None if there are no answers
+pub fn random_word<'a, T: AsRef<str>>(answers: &'a [T], random: &mut Random) -> Option<&'a str> {
+	if answers.is_empty() {
+		return None;
+	}
+	Some(answers[random.gen_range(answers.len() as u64) as usize].as_ref())
+}
+
+impl HostGame {
+	// The hidden word should be one of the answers. The answers and the allowed guesses can be guessed
+	pub fn new<T: AsRef<str>>(
+		hidden_word: &str,
+		answers: &[T],
+		allowed_guesses: &mut dyn Iterator<Item = &T>,
+	) -> Result<Self, String> {
+		if !answers.iter().any(|x| x.as_ref() == hidden_word) {
+			return Err(format!("The word \"{}\" isn't an answer", hidden_word));
+		}
+		let vocabulary = answers
+			.iter()
+			.map(|x| x.as_ref().to_owned())
+			.chain(allowed_guesses.map(|x| x.as_ref().to_owned()))
+			.collect();
+		Ok(Self {
+			hidden_word: hidden_word.to_owned(),
+			vocabulary,
+			max_guesses: DEFAULT_MAX_GUESSES,
+			guesses: Vec::new(),
+			hard_mode: false,
+			revealed: RevealedInfo::default(),
+		})
+	}
+
+	pub fn with_max_guesses(mut self, max_guesses: usize) -> Self {
+		self.max_guesses = max_guesses;
+		self
+	}
+
+	pub fn with_hard_mode(mut self, hard_mode: bool) -> Self {
+		self.hard_mode = hard_mode;
+		self
+	}
+
+	// The answer to the guess. Unknown words and words that break the hard mode aren't counted as guesses
+	pub fn guess(&mut self, word: &str) -> Result<Vec<CharResult>, String> {
+		if self.is_over() {
+			return Err("The game is over".to_owned());
+		}
+		if !self.vocabulary.contains(word) {
+			return Err(format!("The word \"{}\" isn't in the vocabulary", word));
+		}
+		if self.hard_mode {
+			self.revealed.check(word)?;
+		}
+		let answer = calc_answer_vector(word, &self.hidden_word);
+		self.revealed.update(word, &answer);
+		self.guesses.push((word.to_owned(), answer.clone()));
+		Ok(answer)
+	}
+
+	pub fn is_won(&self) -> bool {
+		self.guesses
+			.last()
+			.is_some_and(|(word, _)| *word == self.hidden_word)
+	}
+
+	pub fn is_over(&self) -> bool {
+		self.is_won() || self.guesses.len() >= self.max_guesses
+	}
+
+	pub fn hidden_word(&self) -> &str {
+		&self.hidden_word
+	}
+
+	pub fn guesses(&self) -> &[(String, Vec<CharResult>)] {
+		&self.guesses
+	}
+
+	pub fn max_guesses(&self) -> usize {
+		self.max_guesses
+	}
+
+	// The information that guesses should use, if the game is in the hard mode
+	pub fn hard_mode_info(&self) -> Option<&RevealedInfo> {
+		self.hard_mode.then_some(&self.revealed)
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_host_game() {
+		let answers = ["abcd", "abce", "bcda", "dcba"];
+		let random = |seed| random_word(&answers, &mut Random::new(seed)).unwrap();
+		// The same seed gives the same word, all words can be hidden
+		assert_eq!(random(7), random(7));
+		let hidden: HashSet<_> = (0..100).map(random).collect();
+		assert_eq!(hidden.len(), answers.len());
+		assert!(random_word::<&str>(&[], &mut Random::new(1)).is_none());
+
+		let new_game = |seed| HostGame::new(random(seed), &answers, &mut ["xyzw"].iter()).unwrap();
+
+		let mut game = new_game(7).with_max_guesses(3);
+		let hidden_word = game.hidden_word().to_owned();
+		assert!(game.guess("qwer").is_err());
+		assert_eq!(game.guess("xyzw").unwrap(), [CharResult::NotPresented; 4]);
+		assert!(!game.is_over());
+		assert_eq!(
+			game.guess(&hidden_word).unwrap(),
+			[CharResult::FullMatch; 4]
+		);
+		assert!(game.is_won() && game.is_over());
+		assert!(game.guess(&hidden_word).is_err());
+		assert_eq!(game.guesses().len(), 2);
+
+		// Unknown words aren't counted
+		let mut game = new_game(7).with_max_guesses(2);
+		for _ in 0..2 {
+			assert!(game.guess("qwer").is_err());
+			game.guess("xyzw").unwrap();
+		}
+		assert!(game.is_over() && !game.is_won());
+	}
+
+	#[test]
+	fn test_hard_mode() {
+		let answers = ["abcd"];
+		let mut game = HostGame::new("abcd", &answers, &mut ["abce", "xyzw"].iter())
+			.unwrap()
+			.with_hard_mode(true);
+		assert!(game.hard_mode_info().is_some());
+		game.guess("abce").unwrap();
+		assert_eq!(
+			game.guess("xyzw").unwrap_err(),
+			"The letter a should be at the position 1"
+		);
+		assert_eq!(game.guesses().len(), 1);
+		game.guess("abcd").unwrap();
+		assert!(game.is_won());
+
+		let game = HostGame::new("abcd", &answers, &mut [].iter()).unwrap();
+		assert!(game.hard_mode_info().is_none());
+		assert!(HostGame::new("abce", &answers, &mut ["abce"].iter()).is_err());
+	}
+}
@@ -1,4 +1,5 @@
-use super::colored_string::{Color, ColoredString};
+use super::colored_string::ColoredString;
+use super::hard_mode::RevealedInfo;
 use super::words_chooser::CharResult;
 use std::io::Write;
 
@@ -28,7 +29,12 @@ impl InputGetter {
 		InputGetter { word_len }
 	}
 
-	pub fn get_word<T: AsRef<str>>(&self, msg: T) -> InputResult<String> {
+	// In the hard mode the word should use the revealed information, it's asked again otherwise
+	pub fn get_word<T: AsRef<str>>(
+		&self,
+		msg: T,
+		hard_mode: Option<&RevealedInfo>,
+	) -> InputResult<String> {
 		loop {
 			print!("{0}", msg.as_ref());
 			std::io::stdout().flush()?;
@@ -43,6 +49,13 @@ impl InputGetter {
 
 					continue;
 				}
+				if let Some(Err(err)) = hard_mode.map(|x| x.check(s)) {
+					println!(
+						"{}. In the hard mode you have to use all revealed letters",
+						err
+					);
+					continue;
+				}
 			}
 
 			return r;
@@ -91,19 +104,8 @@ impl InputGetter {
 						.collect();
 
 					if let Some(attempt_word) = &attempt_word {
-						let mut colored_string =
-							ColoredString::new(attempt_word.as_ref().to_owned());
-						for (i, c) in vec_res.iter().enumerate() {
-							match c {
-								CharResult::FullMatch => colored_string.set_color(i, Color::Green),
-								CharResult::PartialMatch => {
-									colored_string.set_color(i, Color::Yellow)
-								}
-								CharResult::NotPresented => {
-									colored_string.set_color(i, Color::Gray)
-								}
-							}
-						}
+						let colored_string =
+							ColoredString::from_answer(attempt_word.as_ref(), &vec_res);
 						if is_accepted(&colored_string)? {
 							return Ok(Input::Value(vec_res));
 						} else {
@@ -1,9 +1,17 @@
 use std::env;
 mod colored_string;
+mod daily;
 mod db_reader;
+mod evaluation;
+mod hard_mode;
+mod host;
 mod input_getter;
+mod random;
+mod scoring;
 mod words_chooser;
-use crate::words_chooser::CharResult;
+use crate::colored_string::ColoredString;
+use crate::scoring::ScoringStrategy;
+use crate::words_chooser::{CharResult, FeedbackRule};
 use input_getter::{Command, Input, InputGetter};
 
 enum BotRunResult {
@@ -21,7 +29,7 @@ fn get_word_len() -> Option<usize> {
 			if word_len_arg {
 				let tmp_res = arg.trim().parse::<usize>();
 				if let Ok(res) = tmp_res {
-					if res >= 4 && res <= 8 {
+					if (4..=8).contains(&res) {
 						return Some(res);
 					}
 				}
@@ -29,21 +37,295 @@ fn get_word_len() -> Option<usize> {
 			}
 		}
 	}
-	return None;
+	None
 }
 
-fn get_word_db_name() -> String {
-	let mut db_arg = false;
+// The value that follows the argument with the given name
+fn get_arg_value(name: &str) -> Option<String> {
+	let mut found = false;
 	for arg in env::args() {
-		if arg == "-db" {
-			db_arg = true;
+		if arg == name {
+			found = true;
 		} else {
-			if db_arg {
-				return arg;
+			if found {
+				return Some(arg);
+			}
+		}
+	}
+	None
+}
+
+fn has_arg(name: &str) -> bool {
+	env::args().any(|x| x == name)
+}
+
+fn get_word_db_name() -> String {
+	get_arg_value("-db").unwrap_or("words_db.txt".to_owned())
+}
+
+// The rule of answers is set by "-rule <official|ambiguous|counts>", it's official by default
+fn get_feedback_rule() -> Option<FeedbackRule> {
+	match get_arg_value("-rule") {
+		Some(name) => FeedbackRule::from_name(&name),
+		None => Some(FeedbackRule::Official),
+	}
+}
+
+// The strategy is set by "-strategy <name>", see ScoringStrategy. It's MaxBucket by default
+fn get_scoring() -> Option<ScoringStrategy> {
+	match get_arg_value("-strategy") {
+		Some(name) => ScoringStrategy::from_name(&name),
+		None => Some(ScoringStrategy::MaxBucket),
+	}
+}
+
+// The average count of guesses, "n/a" if no word was guessed
+fn format_avg(res: &evaluation::EvaluationResult) -> String {
+	match res.avg() {
+		Some(avg) => format!("{:.4}", avg),
+		None => "n/a".to_owned(),
+	}
+}
+
+// Play with every word of the database as the hidden one by every strategy
+fn compare_strategies(chooser: &words_chooser::WordsChooser, words: &[String]) {
+	for scoring in ScoringStrategy::ALL {
+		let res = evaluation::evaluate(&chooser.clone().with_scoring(scoring), words);
+		println!(
+			"Strategy: {:?}. Average guesses: {}, max: {}, not guessed: {}, time: {:?}",
+			scoring,
+			format_avg(&res),
+			res.max_guesses(),
+			res.lost().len(),
+			res.time
+		);
+	}
+}
+
+// Play with every word of the database as the hidden one and print the statistics
+fn analyze(chooser: &words_chooser::WordsChooser, words: &[String]) {
+	// Limit of guesses in the original game
+	const MAX_GUESSES: usize = 6;
+	const WORST_WORDS_COUNT: usize = 10;
+
+	let res = evaluation::evaluate(chooser, words);
+	println!("Words: {}", words.len());
+	println!("Average guesses: {}", format_avg(&res));
+	println!("Guesses distribution:");
+	for (guesses, count) in res.histogram().into_iter().enumerate().skip(1) {
+		println!("{:>3}: {}", guesses, count);
+	}
+	println!(
+		"Not guessed with {} guesses: {}",
+		MAX_GUESSES,
+		res.failures(MAX_GUESSES)
+	);
+	let worst: Vec<_> = res
+		.worst_words(WORST_WORDS_COUNT)
+		.into_iter()
+		.map(|(word, x)| match x {
+			Some(x) => format!("{} ({})", word, x),
+			None => format!("{} (not guessed)", word),
+		})
+		.collect();
+	println!("The worst words: {}", worst.join(", "));
+	println!("Time: {:?}", res.time);
+}
+
+// The program hides the word, the user guesses it. The hints are the attempts of the bot in the same position.
+// Return true if you want to continue
+fn user_game(
+	game: &mut host::HostGame,
+	mut hints: Option<words_chooser::WordsChooser>,
+	input_getter: &InputGetter,
+) -> std::io::Result<bool> {
+	while !game.is_over() {
+		if let Some(hint) = hints.as_ref().and_then(|x| x.suggest()) {
+			println!("Hint: {}", hint);
+		}
+		let msg = format!(
+			"Attempt {} of {}: ",
+			game.guesses().len() + 1,
+			game.max_guesses()
+		);
+		let word = match input_getter.get_word(msg, game.hard_mode_info())? {
+			Input::Value(s) => s,
+			Input::Cmd(Command::Quit) => return Ok(false),
+			Input::Cmd(Command::StopGame) => {
+				println!("The hidden word is {}", game.hidden_word());
+				return Ok(true);
+			}
+		};
+		match game.guess(&word) {
+			Ok(answer) => {
+				println!("{}", ColoredString::from_answer(&word, &answer));
+				if let Some(hints) = hints.as_mut() {
+					hints.apply_answer(&word, &answer).unwrap();
+				}
+			}
+			Err(err) => println!("{}", err),
+		}
+	}
+	if game.is_won() {
+		println!("You won with {} attempts", game.guesses().len());
+	} else {
+		println!("You lost. The hidden word is {}", game.hidden_word());
+	}
+	Ok(true)
+}
+
+fn get_seed() -> Result<Option<u64>, String> {
+	match get_arg_value("-seed") {
+		Some(v) => v
+			.parse()
+			.map(Some)
+			.map_err(|_| format!("Invalid seed {}", v)),
+		None => Ok(None),
+	}
+}
+
+// The limit of guesses is set by "-max-guesses <n>", it's the same as in the original game by default
+fn get_max_guesses() -> Result<usize, String> {
+	match get_arg_value("-max-guesses").map(|x| x.parse::<usize>()) {
+		Some(Ok(n)) if n > 0 => Ok(n),
+		None => Ok(host::DEFAULT_MAX_GUESSES),
+		_ => Err("Invalid limit of guesses".to_owned()),
+	}
+}
+
+// The answers of the daily puzzle if "-answers" isn't given
+const DEFAULT_DAILY_ANSWERS: &str = "daily_answers.txt";
+
+// The daily puzzle: the hidden word depends only on the date ("-date YYYY-MM-DD", today by default),
+// the seed ("-seed <n>", 0 by default) and the answers, so everyone with the same answers plays the same puzzle.
+// The answers are read from "-answers <file>". The database grows with the words added by the user,
+// so it isn't used for the answers, otherwise a new word would change the puzzles of all dates
+fn daily_puzzle(
+	db: &db_reader::WordsDb,
+	chooser: &words_chooser::WordsChooser,
+	word_len: usize,
+	hard_mode: bool,
+) -> Result<(), String> {
+	let date = match get_arg_value("-date") {
+		Some(s) => daily::Date::parse(&s)?,
+		None => daily::Date::today(),
+	};
+	let seed = get_seed()?.unwrap_or(0);
+	let path = std::path::Path::new(".")
+		.join(get_arg_value("-answers").unwrap_or(DEFAULT_DAILY_ANSWERS.to_owned()));
+	let answers = db_reader::load_words(&path, word_len)
+		.map_err(|err| format!("Unable to read the answers {:?}: {}", path, err))?;
+	let hidden_word =
+		daily::word_of_the_day(&answers, date, seed).ok_or("There are no words to hide")?;
+	// The words of the database can be guessed too
+	let mut allowed = db.words_iter().chain(db.allowed_guesses_iter());
+	let mut game = host::HostGame::new(&hidden_word, &answers, &mut allowed)?
+		.with_max_guesses(get_max_guesses()?)
+		.with_hard_mode(hard_mode);
+	println!("The puzzle of {}", date);
+	let hints = has_arg("-hints").then(|| chooser.clone());
+	user_game(&mut game, hints, &InputGetter::new(word_len)).map_err(|x| x.to_string())?;
+	if game.is_over() {
+		println!();
+		println!("{}", daily::share_grid(&format!("Wordle {}", date), &game));
+	}
+	Ok(())
+}
+
+// Play the games where the program hides the words until the user quits
+fn play_with_user(
+	db: &db_reader::WordsDb,
+	chooser: &words_chooser::WordsChooser,
+	word_len: usize,
+	hard_mode: bool,
+) {
+	// "-seed <n>" makes the hidden words reproducible
+	let (seed, max_guesses) = match (get_seed(), get_max_guesses()) {
+		(Ok(seed), Ok(max_guesses)) => (seed, max_guesses),
+		(Err(err), _) | (_, Err(err)) => {
+			println!("{}", err);
+			return;
+		}
+	};
+	let seed = seed.unwrap_or_else(|| {
+		std::time::SystemTime::now()
+			.duration_since(std::time::UNIX_EPOCH)
+			.map_or(0, |x| x.as_nanos() as u64)
+	});
+	let mut random = random::Random::new(seed);
+	let words: Vec<_> = db.words_iter().cloned().collect();
+	let input_getter = InputGetter::new(word_len);
+	loop {
+		let Some(hidden_word) = host::random_word(&words, &mut random) else {
+			println!("There are no words to hide");
+			return;
+		};
+		let mut game =
+			match host::HostGame::new(hidden_word, &words, &mut db.allowed_guesses_iter()) {
+				Ok(game) => game.with_max_guesses(max_guesses).with_hard_mode(hard_mode),
+				Err(err) => {
+					println!("{}", err);
+					return;
+				}
+			};
+		let hints = has_arg("-hints").then(|| chooser.clone());
+		if !user_game(&mut game, hints, &input_getter).unwrap() {
+			break;
+		}
+	}
+}
+
+// The user plays the game elsewhere and enters the guesses and the answers to them.
+// The program shows the words that can be hidden and recommends the next guess
+fn assist(chooser: &words_chooser::WordsChooser, word_len: usize) -> std::io::Result<()> {
+	// Count of the best candidates that are shown
+	const CANDIDATES_LIMIT: usize = 20;
+
+	let input_getter = InputGetter::new(word_len);
+	'games: loop {
+		let mut chooser = chooser.clone();
+		loop {
+			let scores = chooser.score_candidates();
+			match scores.len() {
+				0 => println!("No words of the database match the answers"),
+				1 => println!("The hidden word is {}", scores[0].0),
+				n => {
+					let best: Vec<_> = scores
+						.iter()
+						.take(CANDIDATES_LIMIT)
+						.map(|(word, score)| format!("{} ({:.3})", word, score))
+						.collect();
+					println!("Candidates: {}. The best of them: {}", n, best.join(", "));
+				}
+			}
+			if let Some(word) = chooser.suggest() {
+				println!("Recommended guess: {}", word);
+			}
+
+			let word = match input_getter.get_word("Your guess: ", chooser.hard_mode_info())? {
+				Input::Value(s) => s,
+				Input::Cmd(Command::Quit) => return Ok(()),
+				Input::Cmd(Command::StopGame) => continue 'games,
+			};
+			let answer =
+				match input_getter.get_response_vector(Some(&word), "Answer to the guess: ")? {
+					Input::Value(v) => v,
+					Input::Cmd(Command::Quit) => return Ok(()),
+					Input::Cmd(Command::StopGame) => continue 'games,
+				};
+			if answer.iter().all(|x| x == &CharResult::FullMatch) {
+				println!("Solved. Start the next game");
+				continue 'games;
+			}
+			let prev = chooser.clone();
+			chooser.apply_answer(&word, &answer).unwrap();
+			// Probably it's a typo, the answers that contradict the previous ones aren't accepted
+			if chooser.candidates_count() == 0 && prev.candidates_count() != 0 {
+				println!("No words of the database match this answer, it's ignored");
+				chooser = prev;
 			}
 		}
 	}
-	return "words_db.txt".to_owned();
 }
 
 fn bot_game(
@@ -51,16 +333,15 @@ fn bot_game(
 	input_getter: &input_getter::InputGetter,
 ) -> std::io::Result<BotRunResult> {
 	loop {
-		let cur_word;
-		match strategy.make_guess() {
+		let cur_word = match strategy.make_guess() {
 			Some(word) => {
 				println!("Bot's attempt: {}", word);
-				cur_word = word.to_owned();
+				word.to_owned()
 			}
 			None => {
 				return Ok(BotRunResult::BotLost);
 			}
-		}
+		};
 
 		let ans = input_getter.get_response_vector(Some(&cur_word), "Type answer to attempt: ")?;
 
@@ -98,7 +379,7 @@ fn one_game(
 	}
 
 	loop {
-		match input_getter.get_word("Your attempt: ")? {
+		match input_getter.get_word("Your attempt: ", None)? {
 			Input::Cmd(c) => match c {
 				Command::Quit => return Ok(false),
 				Command::StopGame => return Ok(true),
@@ -112,19 +393,88 @@ fn one_game(
 }
 
 fn main() {
-	let word_len;
-	match get_word_len() {
-		Some(w) => word_len = w,
+	let word_len = match get_word_len() {
+		Some(w) => w,
 		None => {
 			println!("The length of words is not set. Call the program with -wl <len> arguments. Len should be between 4 and 8");
 			return;
 		}
+	};
+	let feedback_rule = match get_feedback_rule() {
+		Some(r) => r,
+		None => {
+			println!(
+				"Unknown rule of answers. Use -rule official, -rule ambiguous or -rule counts"
+			);
+			return;
+		}
+	};
+	let scoring = match get_scoring() {
+		Some(s) => s,
+		None => {
+			let names: Vec<_> = ScoringStrategy::ALL
+				.iter()
+				.map(|x| format!("{:?}", x))
+				.collect();
+			println!("Unknown strategy. Use one of: {}", names.join(", "));
+			return;
+		}
+	};
+	// In the hard mode every attempt uses all revealed letters, it needs the positions of matches
+	let hard_mode = has_arg("-hard");
+	if hard_mode && feedback_rule == FeedbackRule::Counts {
+		println!("The hard mode can't be used with the counts rule");
+		return;
 	}
 	let word_db_path = std::path::Path::new(".").join(get_word_db_name());
 	let mut db = db_reader::WordsDb::new(word_db_path.as_path(), word_len).unwrap();
+	// The database contains the words that can be hidden, "-guesses <file>" adds the words that are only guessed
+	if let Some(name) = get_arg_value("-guesses") {
+		if let Err(err) = db.load_allowed_guesses(&std::path::Path::new(".").join(name)) {
+			println!("Unable to read the allowed guesses: {}", err);
+			return;
+		}
+	}
 	loop {
 		db.sync_new_words();
-		let mut strategy = words_chooser::WordsChooser::new(&mut db.words_iter());
+		let mut strategy =
+			match words_chooser::WordsChooser::new(&mut db.words_iter(), Some(word_len))
+				.and_then(|s| s.with_allowed_guesses(&mut db.allowed_guesses_iter()))
+				.and_then(|s| s.with_weights(&mut db.weights_iter()))
+			{
+				Ok(s) => s
+					.with_feedback_rule(feedback_rule)
+					.with_scoring(scoring)
+					.with_hard_mode(hard_mode),
+				Err(err) => {
+					println!("Invalid words database: {}", err);
+					return;
+				}
+			};
+		if has_arg("-compare") {
+			let words: Vec<_> = db.words_iter().cloned().collect();
+			compare_strategies(&strategy, &words);
+			return;
+		}
+		if has_arg("-assist") {
+			assist(&strategy, word_len).unwrap();
+			return;
+		}
+		if has_arg("-daily") {
+			if let Err(err) = daily_puzzle(&db, &strategy, word_len, hard_mode) {
+				println!("{}", err);
+			}
+			return;
+		}
+		if has_arg("-play") {
+			play_with_user(&db, &strategy, word_len, hard_mode);
+			return;
+		}
+		if has_arg("--analyze") {
+			let words: Vec<_> = db.words_iter().cloned().collect();
+			analyze(&strategy, &words);
+			return;
+		}
 
 		if !one_game(&mut db, &mut strategy, word_len).unwrap() {
 			break;
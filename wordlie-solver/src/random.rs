@@ -0,0 +1,61 @@
+// Small pseudo-random generator (xorshift64*). It isn't cryptographically secure,
+// but it's fast and reproducible for the given seed
+#[derive(Clone, Debug)]
+pub struct Random {
+	state: u64,
+}
+
+impl Random {
+	pub fn new(seed: u64) -> Self {
+		// Mix the seed (splitmix64), so close seeds give different sequences. Zero state is forbidden
+		let mut z = seed.wrapping_add(0x9e3779b97f4a7c15);
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+		z ^= z >> 31;
+		Self {
+			state: if z == 0 { 1 } else { z },
+		}
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state ^= self.state >> 12;
+		self.state ^= self.state << 25;
+		self.state ^= self.state >> 27;
+		self.state.wrapping_mul(0x2545f4914f6cdd1d)
+	}
+
+	// Random number from 0 to n - 1, n must be positive
+	pub fn gen_range(&mut self, n: u64) -> u64 {
+		((self.next_u64() as u128 * n as u128) >> 64) as u64
+	}
+
+	pub fn shuffle<T>(&mut self, a: &mut [T]) {
+		for i in (1..a.len()).rev() {
+			let j = self.gen_range(i as u64 + 1) as usize;
+			a.swap(i, j);
+		}
+	}
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_random() {
+		let mut a = Random::new(42);
+		let mut b = Random::new(42);
+		let v: Vec<_> = (0..100).map(|_| a.gen_range(10)).collect();
+		assert_eq!(v, (0..100).map(|_| b.gen_range(10)).collect::<Vec<_>>());
+		assert!(v.iter().all(|x| *x < 10));
+		// All values appear with a reasonable probability
+		assert!((0..10).all(|x| v.contains(&x)));
+		assert_ne!(Random::new(1).next_u64(), Random::new(2).next_u64());
+
+		let mut p: Vec<_> = (0..20).collect();
+		a.shuffle(&mut p);
+		assert_ne!(p, (0..20).collect::<Vec<_>>());
+		p.sort();
+		assert_eq!(p, (0..20).collect::<Vec<_>>());
+	}
+}
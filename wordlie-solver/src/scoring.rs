@@ -0,0 +1,101 @@
+// How the attempt is chosen by the distribution of candidates over the answers.
+// Every strategy gives a score to the distribution, the attempt with the smallest score is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScoringStrategy {
+	// Minimize the largest set of candidates left after the answer, i.e. the worst case
+	MaxBucket,
+	// Minimize the expected count of candidates left after the answer
+	SumOfSquares,
+	// Maximize the expected amount of information given by the answer
+	Entropy,
+	// Landy's formula: every set of n candidates costs n * x where x^x = n
+	Landy,
+	// Minimize the expected count of guesses, looking one guess ahead. Only the best attempts by entropy are checked
+	Lookahead,
+}
+
+impl ScoringStrategy {
+	pub const ALL: [ScoringStrategy; 5] = [
+		ScoringStrategy::MaxBucket,
+		ScoringStrategy::SumOfSquares,
+		ScoringStrategy::Entropy,
+		ScoringStrategy::Landy,
+		ScoringStrategy::Lookahead,
+	];
+
+	pub fn from_name(name: &str) -> Option<Self> {
+		Self::ALL
+			.into_iter()
+			.find(|t| format!("{:?}", t).eq_ignore_ascii_case(name))
+	}
+
+	// Score of the distribution: buckets contain weights of candidates for every answer, most of them are zeros.
+	// With equal weights of candidates the weight of the bucket is the count of candidates in it.
+	// Lookahead needs more than the distribution, so the distribution is scored by entropy
+	pub fn score(self, buckets: &[f64]) -> f64 {
+		let non_empty = buckets.iter().filter(|x| **x != 0.0).copied();
+		match self {
+			ScoringStrategy::MaxBucket => non_empty.fold(0.0, f64::max),
+			ScoringStrategy::SumOfSquares => non_empty.map(|x| x * x).sum(),
+			ScoringStrategy::Entropy | ScoringStrategy::Lookahead => {
+				let total: f64 = buckets.iter().sum();
+				non_empty
+					.map(|x| {
+						let p = x / total;
+						p * f64::log2(p)
+					})
+					.sum()
+			}
+			ScoringStrategy::Landy => non_empty.map(|x| x * inv_self_power(x)).sum(),
+		}
+	}
+}
+
+// Solution of x^x = n, it's found by binary search
+fn inv_self_power(n: f64) -> f64 {
+	let mut x: f64 = 1.0;
+	let mut y: f64 = 2.0;
+	while y.powf(y) <= n {
+		y *= 2.0;
+	}
+	while y - x > 1e-9 {
+		let z = (x + y) / 2.0;
+		if z.powf(z) <= n {
+			x = z;
+		} else {
+			y = z;
+		}
+	}
+	x
+}
+
+// Rough estimate of the count of guesses needed to find one of n candidates if nothing else is known:
+// they are tried one by one. It's exact for one and two candidates
+pub fn rough_guesses(n: usize) -> f64 {
+	(n as f64 + 1.0) / 2.0
+}
+
+#[cfg(test)]
+mod test {
+	use super::*;
+
+	#[test]
+	fn test_scores() {
+		let buckets = [0.0, 3.0, 1.0, 0.0, 4.0];
+		assert_eq!(ScoringStrategy::MaxBucket.score(&buckets), 4.0);
+		assert_eq!(ScoringStrategy::SumOfSquares.score(&buckets), 26.0);
+		// Every candidate in its own bucket is the best distribution for every strategy
+		for s in ScoringStrategy::ALL {
+			assert!(s.score(&[1.0; 8]) < s.score(&buckets), "{:?}", s);
+		}
+		assert!((ScoringStrategy::Entropy.score(&[2.0, 0.0, 2.0]) + 1.0).abs() < 1e-9);
+		assert!((inv_self_power(27.0) - 3.0).abs() < 1e-6);
+		assert!((ScoringStrategy::Landy.score(&[4.0, 1.0]) - 9.0).abs() < 1e-6);
+
+		assert_eq!(
+			ScoringStrategy::from_name("sumofsquares"),
+			Some(ScoringStrategy::SumOfSquares)
+		);
+		assert_eq!(ScoringStrategy::from_name("unknown"), None);
+	}
+}
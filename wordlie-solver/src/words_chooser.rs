@@ -1,5 +1,8 @@
 use std::{collections::HashMap, hash::Hash};
 
+use crate::hard_mode::RevealedInfo;
+use crate::scoring::{rough_guesses, ScoringStrategy};
+
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Ord, Eq, Hash)]
 pub enum CharResult {
 	NotPresented,
@@ -7,94 +10,441 @@ pub enum CharResult {
 	FullMatch,
 }
 
-#[derive(PartialEq, Debug)]
+// Longer words aren't supported: answers are coded by numbers below 3^len
+pub const MAX_WORD_LEN: usize = 16;
+
+// Count of the best attempts by entropy that are checked by the lookahead strategy.
+// They are also the attempts tried on the next step, together with the candidates left
+const LOOKAHEAD_WIDTH: usize = 10;
+
+// How the answer to an attempt is calculated when letters repeat
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackRule {
+	// The rule of the original Wordle: full matches first, then the rest letters of the attempt
+	// are partial matches from left to right while the hidden word has unmatched equal letters
+	Official,
+	// Any of the equal letters of the attempt can be a partial match, so there can be several possible answers
+	Ambiguous,
+	// Only the numbers of full and partial matches are meaningful, not their positions (like in Mastermind)
+	Counts,
+}
+
+impl FeedbackRule {
+	pub fn from_name(name: &str) -> Option<Self> {
+		[Self::Official, Self::Ambiguous, Self::Counts]
+			.into_iter()
+			.find(|t| format!("{:?}", t).eq_ignore_ascii_case(name))
+	}
+
+	// Codes of all answers that are possible for the hidden word
+	fn calc_answers(self, attempt_word: &str, hidden_word: &str, res: &mut Vec<u32>) {
+		match self {
+			FeedbackRule::Official => {
+				res.clear();
+				res.push(calc_answer(attempt_word, hidden_word));
+			}
+			FeedbackRule::Ambiguous => calc_all_answers(attempt_word, hidden_word, res),
+			FeedbackRule::Counts => {
+				res.clear();
+				let code = calc_answer(attempt_word, hidden_word);
+				let (full, partial) = count_matches(code, attempt_word.chars().count());
+				res.push(counts_code(full, partial));
+			}
+		}
+	}
+
+	// Code of the given answer, it's one of the codes returned by calc_answers
+	fn answer_code(self, answer: &[CharResult]) -> u32 {
+		match self {
+			FeedbackRule::Official | FeedbackRule::Ambiguous => convert_res(answer),
+			FeedbackRule::Counts => {
+				let count = |r| answer.iter().filter(|x| **x == r).count();
+				counts_code(
+					count(CharResult::FullMatch),
+					count(CharResult::PartialMatch),
+				)
+			}
+		}
+	}
+}
+
+#[derive(PartialEq, Debug, Clone)]
 enum ChoiseState {
 	ReadyToMakeGuess,
 	WaitForRespond,
 	NoMoreWords,
 }
 
+// Guesses are chosen from the vocabulary, hidden words are from the candidates.
+// Initially the candidates are the answers, which are a part of the vocabulary
+#[derive(Clone)]
 struct WordsContainer {
 	vocabulary: Vec<String>,
 	candidate_words: Vec<String>,
+	// Weights of the answers that are more or less likely than others, the weight of other words is 1
+	weights: HashMap<String, f64>,
 }
 
+#[derive(Clone)]
 pub struct WordsChooser {
 	words_container: WordsContainer,
 	state: ChoiseState,
-	word_len: u32,
+	word_len: usize,
 	last_word: Option<String>,
+	feedback_rule: FeedbackRule,
+	scoring: ScoringStrategy,
+	// In the hard mode only the attempts that use the revealed information are made
+	hard_mode: bool,
+	revealed: RevealedInfo,
 }
 
 impl WordsChooser {
-	pub fn new<T: AsRef<str>>(all_words: &mut dyn Iterator<Item = &T>) -> WordsChooser {
-		WordsChooser {
-			words_container: WordsContainer::new(all_words),
+	// If word_len is None, the length of words is taken from the vocabulary, so it can't be empty.
+	// All words of the vocabulary should have the same length
+	pub fn new<T: AsRef<str>>(
+		all_words: &mut dyn Iterator<Item = &T>,
+		word_len: Option<usize>,
+	) -> Result<WordsChooser, String> {
+		let words_container = WordsContainer::new(all_words);
+		let word_len = match (word_len, words_container.vocabulary.first()) {
+			(Some(word_len), _) => word_len,
+			(None, Some(word)) => word.chars().count(),
+			(None, None) => return Err("The vocabulary is empty".to_owned()),
+		};
+		if word_len > MAX_WORD_LEN {
+			return Err(format!(
+				"Words can't contain more than {} symbols",
+				MAX_WORD_LEN
+			));
+		}
+		if let Some(word) = words_container
+			.vocabulary
+			.iter()
+			.find(|x| x.chars().count() != word_len)
+		{
+			return Err(format!(
+				"The word \"{}\" doesn't contain {} symbols",
+				word, word_len
+			));
+		}
+		Ok(WordsChooser {
+			words_container,
 			state: ChoiseState::ReadyToMakeGuess,
-			word_len: 5, // TODO: make a right value
+			word_len,
 			last_word: None,
+			feedback_rule: FeedbackRule::Official,
+			scoring: ScoringStrategy::MaxBucket,
+			hard_mode: false,
+			revealed: RevealedInfo::default(),
+		})
+	}
+
+	pub fn with_feedback_rule(mut self, feedback_rule: FeedbackRule) -> Self {
+		self.feedback_rule = feedback_rule;
+		self
+	}
+
+	// Words that can be used as guesses, but can't be hidden. The words of the vocabulary are always allowed
+	pub fn with_allowed_guesses<T: AsRef<str>>(
+		mut self,
+		words: &mut dyn Iterator<Item = &T>,
+	) -> Result<Self, String> {
+		let mut known: std::collections::HashSet<_> =
+			self.words_container.vocabulary.iter().cloned().collect();
+		for word in words {
+			let word = word.as_ref();
+			if word.chars().count() != self.word_len {
+				return Err(format!(
+					"The word \"{}\" doesn't contain {} symbols",
+					word, self.word_len
+				));
+			}
+			if known.insert(word.to_owned()) {
+				self.words_container.vocabulary.push(word.to_owned());
+			}
+		}
+		Ok(self)
+	}
+
+	// Words with bigger weights are more likely to be hidden, e.g. weights are frequencies of the words.
+	// Weights are given to the answers, they are normalized so that the average weight is 1
+	pub fn with_weights<T: AsRef<str>>(
+		mut self,
+		weights: &mut dyn Iterator<Item = (&T, f64)>,
+	) -> Result<Self, String> {
+		let answers: std::collections::HashSet<_> = self
+			.words_container
+			.candidate_words
+			.iter()
+			.map(String::as_str)
+			.collect();
+		let mut res = HashMap::new();
+		for (word, weight) in weights {
+			let word = word.as_ref();
+			if !answers.contains(word) {
+				return Err(format!("The word \"{}\" isn't one of the answers", word));
+			}
+			if !(weight.is_finite() && weight > 0.0) {
+				return Err(format!(
+					"The weight of the word \"{}\" should be positive",
+					word
+				));
+			}
+			res.insert(word.to_owned(), weight);
+		}
+		self.words_container.weights = res;
+		let total: f64 = self
+			.words_container
+			.candidate_words
+			.iter()
+			.map(|x| self.words_container.weight(x))
+			.sum();
+		let scale = self.words_container.candidate_words.len() as f64 / total;
+		for w in self.words_container.weights.values_mut() {
+			*w *= scale;
+		}
+		// Words without the given weight have the weight 1 before the normalization
+		for word in self.words_container.candidate_words.iter() {
+			self.words_container
+				.weights
+				.entry(word.clone())
+				.or_insert(scale);
 		}
+		Ok(self)
+	}
+
+	pub fn with_scoring(mut self, scoring: ScoringStrategy) -> Self {
+		self.scoring = scoring;
+		self
+	}
+
+	// The hard mode needs the positions of matches, so it isn't compatible with FeedbackRule::Counts
+	pub fn with_hard_mode(mut self, hard_mode: bool) -> Self {
+		self.hard_mode = hard_mode;
+		self
 	}
 
 	pub fn make_guess(&mut self) -> Option<&str> {
 		if self.state != ChoiseState::ReadyToMakeGuess {
 			panic!("Cannot make guess, invalid state")
 		}
-		if self.words_container.candidate_words.is_empty() {
+		let Some(res_attempt_word) = self.suggest() else {
 			self.state = ChoiseState::NoMoreWords;
 			return None;
+		};
+
+		self.state = ChoiseState::WaitForRespond;
+		self.last_word = Some(res_attempt_word);
+		self.last_word.as_deref()
+	}
+
+	// The best attempt in the current position, it doesn't change the state. It's None if there are no candidates.
+	// In the hard mode there may be no allowed attempts if the answers are inconsistent
+	pub fn suggest(&self) -> Option<String> {
+		if self.words_container.candidate_words.is_empty() {
+			return None;
 		}
-		let mut max_val = u32::MAX;
-		let mut res_attempt_word = "";
-		let total_answers_count = {
-			let mut d = 1;
-			for _ in 0..self.word_len {
-				d *= 3;
-			}
-			d
+		self.find_best_attempt()
+	}
+
+	fn find_best_attempt(&self) -> Option<String> {
+		let mut words = self.words_container.construct_list_of_words();
+		if self.hard_mode {
+			words.retain(|x| self.revealed.check(x).is_ok());
+		}
+		if words.is_empty() {
+			return None;
+		}
+		let scores = self.scores(&words);
+
+		// The first of the equally good attempts is chosen, so candidates win
+		let best = |indexes: &mut dyn Iterator<Item = (usize, f64)>| {
+			indexes
+				.fold(None, |best: Option<(usize, f64)>, (i, x)| match best {
+					Some((_, y)) if y <= x => best,
+					_ => Some((i, x)),
+				})
+				.unwrap()
+				.0
 		};
-		let mut vec_variants = Vec::new();
-		vec_variants.resize(total_answers_count, 0);
+		let mut indexes = scores.iter().copied().enumerate();
+		if self.scoring != ScoringStrategy::Lookahead {
+			return Some(words[best(&mut indexes)].to_owned());
+		}
+		let mut order: Vec<_> = (0..words.len()).collect();
+		order.sort_by(|a, b| scores[*a].total_cmp(&scores[*b]));
+		order.truncate(LOOKAHEAD_WIDTH);
+		order.sort();
+		let top: Vec<_> = order.iter().map(|i| words[*i]).collect();
+		let mut expected = order
+			.into_iter()
+			.map(|i| (i, self.expected_guesses(words[i], &top)));
+		Some(words[best(&mut expected)].to_owned())
+	}
 
-		let mut answers_vec = Vec::new();
+	// Scores of the attempts by the distributions of the candidates over the answers, smaller is better
+	fn scores(&self, words: &[&str]) -> Vec<f64> {
+		// Every letter gives one of three results, so answers are coded by numbers below 3^word_len
+		let total_answers_count = 3usize.pow(self.word_len as u32);
+		let mut vec_variants = vec![0.0; total_answers_count];
+		let weights: Vec<_> = self
+			.words_container
+			.candidate_words
+			.iter()
+			.map(|x| self.words_container.weight(x))
+			.collect();
 
-		for attempt_word in self.words_container.construct_list_of_words() {
+		let mut answers_vec = Vec::new();
+		let mut scores = Vec::new();
+		for attempt_word in words.iter() {
 			for v in vec_variants.iter_mut() {
-				*v = 0
+				*v = 0.0
 			}
-			for hidden_word in self.words_container.candidate_words.iter() {
-				calc_all_answers(attempt_word, hidden_word, &mut answers_vec);
+			for (hidden_word, weight) in self.words_container.candidate_words.iter().zip(&weights) {
+				self.feedback_rule
+					.calc_answers(attempt_word, hidden_word, &mut answers_vec);
 				for res in answers_vec.iter() {
-					vec_variants[*res as usize] += 1;
+					vec_variants[*res as usize] += weight;
 				}
 			}
-			let tmp_val = vec_variants.iter().max();
-			if let Some(tmp_val) = tmp_val {
-				if tmp_val < &max_val {
-					max_val = *tmp_val;
-					res_attempt_word = attempt_word;
-				}
+			scores.push(self.scoring.score(&vec_variants));
+		}
+		scores
+	}
+
+	// The words that can be hidden with their scores as attempts, the best attempts are the first.
+	// The score is given by the scoring strategy to the distribution of the candidates, Lookahead uses entropy
+	pub fn score_candidates(&self) -> Vec<(String, f64)> {
+		let words: Vec<_> = self
+			.words_container
+			.construct_list_of_words()
+			.into_iter()
+			.take(self.words_container.candidate_words.len())
+			.collect();
+		let mut res: Vec<_> = words
+			.iter()
+			.map(|x| x.to_string())
+			.zip(self.scores(&words))
+			.collect();
+		// The sort is stable, so the most likely of the equally good candidates are the first
+		res.sort_by(|a, b| a.1.total_cmp(&b.1));
+		res
+	}
+
+	pub fn candidates_count(&self) -> usize {
+		self.words_container.candidate_words.len()
+	}
+
+	// The information that attempts should use, if the chooser is in the hard mode
+	pub fn hard_mode_info(&self) -> Option<&RevealedInfo> {
+		self.hard_mode.then_some(&self.revealed)
+	}
+
+	// Candidates split by the answers to the attempt. The answer is the first element of the pair
+	fn split<'a>(&self, attempt_word: &str, candidates: &[&'a str]) -> Vec<(u32, Vec<&'a str>)> {
+		let mut answers = Vec::new();
+		let mut pairs = Vec::new();
+		for hidden_word in candidates {
+			self.feedback_rule
+				.calc_answers(attempt_word, hidden_word, &mut answers);
+			pairs.extend(answers.iter().map(|x| (*x, *hidden_word)));
+		}
+		pairs.sort();
+		let mut res: Vec<(u32, Vec<&str>)> = Vec::new();
+		for (answer, word) in pairs {
+			match res.last_mut() {
+				Some((last, group)) if *last == answer => group.push(word),
+				_ => res.push((answer, vec![word])),
 			}
 		}
+		res
+	}
 
-		self.state = ChoiseState::WaitForRespond;
-		self.last_word = Some(res_attempt_word.to_owned());
-		Some(res_attempt_word)
+	// Expected count of guesses to find the hidden word if the attempt is made now. For every answer
+	// the best next attempt is chosen among the given attempts and the candidates left,
+	// after it the candidates are estimated roughly
+	fn expected_guesses(&self, attempt_word: &str, attempts: &[&str]) -> f64 {
+		// All letters are full matches
+		let win = 3u32.pow(self.word_len as u32) - 1;
+		let weight = |group: &[&str]| {
+			group
+				.iter()
+				.map(|x| self.words_container.weight(x))
+				.sum::<f64>()
+		};
+		let expected_rest = |groups: &[(u32, Vec<&str>)], next: &dyn Fn(&[&str]) -> f64| {
+			let total: f64 = groups.iter().map(|(_, x)| weight(x)).sum();
+			groups
+				.iter()
+				.filter(|(answer, _)| *answer != win)
+				.map(|(_, group)| weight(group) / total * next(group))
+				.sum::<f64>()
+		};
+
+		let candidates: Vec<_> = self
+			.words_container
+			.candidate_words
+			.iter()
+			.map(String::as_str)
+			.collect();
+		let groups = self.split(attempt_word, &candidates);
+		let next = |group: &[&str]| {
+			if group.len() == 1 {
+				return 1.0;
+			}
+			attempts
+				.iter()
+				.chain(group)
+				.map(|w| {
+					1.0 + expected_rest(&self.split(w, group), &|x: &[&str]| rough_guesses(x.len()))
+				})
+				.fold(f64::MAX, f64::min)
+		};
+		1.0 + expected_rest(&groups, &next)
 	}
 
 	pub fn respond_to_guess(&mut self, respond: &[CharResult]) {
 		if self.state != ChoiseState::WaitForRespond {
 			panic!("Unexpected state: {:?}", self.state);
 		}
-		let respond = convert_res(respond);
+		if respond.len() != self.word_len {
+			panic!("Length of the respond should be equal to self.word_len");
+		}
+		let last_word = self.last_word.take().unwrap();
+		self.apply_answer(&last_word, respond).unwrap();
+	}
+
+	// Take into account the answer to any attempt, e.g. made by a human in another game.
+	// The attempt that waits for the answer is dropped. The answers may leave no candidates
+	pub fn apply_answer(
+		&mut self,
+		attempt_word: &str,
+		respond: &[CharResult],
+	) -> Result<(), String> {
+		if attempt_word.chars().count() != self.word_len {
+			return Err(format!(
+				"The word \"{}\" doesn't contain {} symbols",
+				attempt_word, self.word_len
+			));
+		}
+		if respond.len() != self.word_len {
+			return Err(format!(
+				"The answer should contain {} results",
+				self.word_len
+			));
+		}
+		self.revealed.update(attempt_word, respond);
+		let respond = self.feedback_rule.answer_code(respond);
 		let mut tmp = Vec::new();
-		let last_word = self.last_word.as_ref().unwrap();
+		let feedback_rule = self.feedback_rule;
 		self.words_container.candidate_words.retain(|x| {
-			calc_all_answers(&last_word, x, &mut tmp);
+			feedback_rule.calc_answers(attempt_word, x, &mut tmp);
 			tmp.contains(&respond)
 		});
 
+		self.last_word = None;
 		self.state = ChoiseState::ReadyToMakeGuess;
+		Ok(())
 	}
 }
 
@@ -105,13 +455,19 @@ impl WordsContainer {
 		Self {
 			vocabulary,
 			candidate_words,
+			weights: HashMap::new(),
 		}
 	}
 
+	fn weight(&self, word: &str) -> f64 {
+		self.weights.get(word).copied().unwrap_or(1.0)
+	}
+
 	// Return all possible words in the order "candidates are first, other last"
 	// This provides the highest priority for the words that match to the previous attempts
 	// Without this ordering the algorithm won't guess the right word if it has only one candidate
-	// left, because any other word won't decrease expected number of candidates
+	// left, because any other word won't decrease expected number of candidates.
+	// The most likely candidates are the first ones, so they are preferred among equally good attempts
 	fn construct_list_of_words(&self) -> Vec<&str> {
 		if self.candidate_words.is_empty() {
 			return Vec::new();
@@ -138,6 +494,7 @@ impl WordsContainer {
 				y -= 1;
 			}
 		}
+		res[..x].sort_by(|a, b| self.weight(b).total_cmp(&self.weight(a)));
 
 		res
 	}
@@ -201,6 +558,98 @@ fn convert_res(arg: &[CharResult]) -> u32 {
 	res
 }
 
+// The answer by the official rule that the host of the game gives
+pub fn calc_answer_vector(attempt_word: &str, hidden_word: &str) -> Vec<CharResult> {
+	let mut code = calc_answer(attempt_word, hidden_word);
+	let mut res = Vec::new();
+	for _ in attempt_word.chars() {
+		res.push(match code % 3 {
+			2 => CharResult::FullMatch,
+			1 => CharResult::PartialMatch,
+			_ => CharResult::NotPresented,
+		});
+		code /= 3;
+	}
+	res
+}
+
+// Numbers of full and partial matches in the answer with the given code
+fn count_matches(mut code: u32, len: usize) -> (usize, usize) {
+	let (mut full, mut partial) = (0, 0);
+	for _ in 0..len {
+		match code % 3 {
+			2 => full += 1,
+			1 => partial += 1,
+			_ => {}
+		}
+		code /= 3;
+	}
+	(full, partial)
+}
+
+// Code of the answer where full matches are the first letters and partial matches are the next ones
+fn counts_code(full: usize, partial: usize) -> u32 {
+	let mut res = 0;
+	let mut delta = 1;
+	for i in 0..full + partial {
+		res += if i < full { 2 * delta } else { delta };
+		delta *= 3;
+	}
+	res
+}
+
+// Calculates the answer by the official rule, see FeedbackRule::Official.
+// It's called for every pair of words, so it doesn't allocate memory
+fn calc_answer(attempt_word: &str, hidden_word: &str) -> u32 {
+	// Letters of the hidden word that aren't matched yet, and letters of the attempt that aren't full matches.
+	// Matched letters are replaced by zero characters, words don't contain them
+	let mut attempt = ['\0'; MAX_WORD_LEN];
+	let mut hidden = ['\0'; MAX_WORD_LEN];
+	let mut attempt_chars = attempt_word.chars();
+	let mut hidden_chars = hidden_word.chars();
+	let mut len = 0;
+	loop {
+		match (attempt_chars.next(), hidden_chars.next()) {
+			(Some(a), Some(h)) => {
+				if len == MAX_WORD_LEN {
+					panic!("The word is too long")
+				}
+				attempt[len] = a;
+				hidden[len] = h;
+				len += 1;
+			}
+			(None, None) => break,
+			_ => panic!("Cannot compare strings with different lenght"),
+		}
+	}
+
+	let mut pow3 = [0; MAX_WORD_LEN];
+	let mut delta = 1;
+	for p in pow3.iter_mut().take(len) {
+		*p = delta;
+		delta *= 3;
+	}
+
+	let mut res = 0;
+	for i in 0..len {
+		if attempt[i] == hidden[i] {
+			res += 2 * pow3[i];
+			attempt[i] = '\0';
+			hidden[i] = '\0';
+		}
+	}
+	for i in 0..len {
+		if attempt[i] == '\0' {
+			continue;
+		}
+		if let Some(j) = hidden[..len].iter().position(|h| *h == attempt[i]) {
+			res += pow3[i];
+			hidden[j] = '\0';
+		}
+	}
+	res
+}
+
 // Calculates all possible answers for the given hidden_word if we try an attempt_word as an attempt
 // The main reason why we need to return vector instead of only one result is a letter repetitions
 // For example, if the hidden word is "abba", and the attempt word is "baaa",
@@ -245,28 +694,21 @@ fn calc_all_answers(attempt_word: &str, hidden_word: &str, res: &mut Vec<u32>) {
 
 			// Both vectors are sorted, so we can compare elements one-by-one by moving pointers and ignoring equal items
 			// "Pointer" here is just the last element in the vector
-			loop {
-				match (attempt_pos.last(), hidden_pos.last()) {
-					(Some(a), Some(h)) => {
-						let va = *a;
-						let vh = *h;
-						if va <= vh {
-							hidden_pos.pop();
-						} else {
-							new_attempt_pos.push(va);
-						}
-						if va >= vh {
-							attempt_pos.pop();
-						}
-						if va == vh {
-							// If we are here, characters at position va are equal
-							for r_v in res.iter_mut() {
-								*r_v += pow3[va] * 2;
-							}
-						}
-					}
-					(_, _) => {
-						break;
+			while let (Some(a), Some(h)) = (attempt_pos.last(), hidden_pos.last()) {
+				let va = *a;
+				let vh = *h;
+				if va <= vh {
+					hidden_pos.pop();
+				} else {
+					new_attempt_pos.push(va);
+				}
+				if va >= vh {
+					attempt_pos.pop();
+				}
+				if va == vh {
+					// If we are here, characters at position va are equal
+					for r_v in res.iter_mut() {
+						*r_v += pow3[va] * 2;
 					}
 				}
 			}
@@ -309,6 +751,7 @@ fn calc_all_answers(attempt_word: &str, hidden_word: &str, res: &mut Vec<u32>) {
 mod test {
 
 	use super::*;
+	use std::collections::HashSet;
 
 	fn calc_all_answers_test(attempt_word: &str, hidden_word: &str) -> Vec<u32> {
 		let mut res = Vec::new();
@@ -392,7 +835,7 @@ mod test {
 			_ => panic!("Invalid argument: {}", x),
 		};
 
-		let res_expected = vec![
+		let res_expected = [
 			[0, 1, 2, 1].iter().map(f).collect::<Vec<_>>(),
 			[1, 1, 2, 0].iter().map(f).collect::<Vec<_>>(),
 		];
@@ -439,22 +882,107 @@ mod test {
 		assert_eq!(r, res_expected);
 	}
 
+	// Answers are strings of "0", "1" and "2" like in the input
+	fn answer_code(s: &str) -> u32 {
+		s.chars()
+			.rev()
+			.fold(0, |acc, c| acc * 3 + c.to_digit(3).unwrap())
+	}
+
+	#[test]
+	fn test_official_rule() {
+		for (attempt_word, hidden_word, answer) in [
+			("keeps", "abbey", "01000"),
+			("geese", "those", "00022"),
+			("babes", "abbey", "11220"),
+			("speed", "abide", "00101"),
+			("eerie", "there", "10102"),
+			("aaaaa", "abbey", "20000"),
+			("llama", "hello", "11000"),
+			("sassy", "essay", "11202"),
+			("слово", "весло", "11012"),
+		] {
+			assert_eq!(
+				calc_answer(attempt_word, hidden_word),
+				answer_code(answer),
+				"{} {}",
+				attempt_word,
+				hidden_word
+			);
+		}
+	}
+
+	#[test]
+	fn test_rules_agree() {
+		// All words of 4 letters from the alphabet of 3 letters, so most of them have repeated letters
+		let words: Vec<String> = (0..81)
+			.map(|i: u32| {
+				(0..4)
+					.map(|j| ['a', 'b', 'c'][(i / 3u32.pow(j) % 3) as usize])
+					.collect()
+			})
+			.collect();
+		let mut answers = Vec::new();
+		for attempt_word in words.iter() {
+			for hidden_word in words.iter() {
+				let official = calc_answer(attempt_word, hidden_word);
+				FeedbackRule::Ambiguous.calc_answers(attempt_word, hidden_word, &mut answers);
+				assert!(answers.contains(&official));
+				// Without repeated letters in the attempt the answer isn't ambiguous
+				if attempt_word.chars().collect::<HashSet<_>>().len() == 4 {
+					assert_eq!(answers, [official]);
+				}
+
+				// Numbers of matches are the same for all possible answers
+				FeedbackRule::Counts.calc_answers(attempt_word, hidden_word, &mut answers);
+				let (full, partial) = count_matches(answers[0], 4);
+				let common: usize = ['a', 'b', 'c']
+					.iter()
+					.map(|c| {
+						let count = |w: &String| w.chars().filter(|x| x == c).count();
+						usize::min(count(attempt_word), count(hidden_word))
+					})
+					.sum();
+				assert_eq!(full + partial, common);
+				assert_eq!(count_matches(official, 4), (full, partial));
+			}
+		}
+	}
+
+	#[test]
+	fn test_counts_rule() {
+		let vocabulary: Vec<_> = ["abcd", "bacd", "abce", "cdab", "efgh"]
+			.iter()
+			.map(|x| x.to_string())
+			.collect();
+		let mut w = WordsChooser::new(&mut vocabulary.iter(), None)
+			.unwrap()
+			.with_feedback_rule(FeedbackRule::Counts);
+		w.make_guess().unwrap();
+		w.last_word = Some("abcd".to_owned());
+		// Positions of matches don't matter
+		w.respond_to_guess(&[
+			CharResult::PartialMatch,
+			CharResult::FullMatch,
+			CharResult::PartialMatch,
+			CharResult::FullMatch,
+		]);
+		assert_eq!(w.words_container.candidate_words, ["bacd"]);
+
+		assert_eq!(
+			FeedbackRule::from_name("counts"),
+			Some(FeedbackRule::Counts)
+		);
+		assert_eq!(FeedbackRule::from_name("other"), None);
+	}
+
 	#[test]
 	fn test_no_choise() {
 		let vocabulary: Vec<_> = ["abc", "abd", "bad"]
 			.iter()
 			.map(|x| x.to_string())
 			.collect();
-		let candidate_words = vocabulary.clone();
-		let mut w = WordsChooser {
-			words_container: WordsContainer {
-				vocabulary,
-				candidate_words,
-			},
-			state: ChoiseState::ReadyToMakeGuess,
-			word_len: 3,
-			last_word: None,
-		};
+		let mut w = WordsChooser::new(&mut vocabulary.iter(), None).unwrap();
 
 		assert!(w.make_guess().is_some());
 		assert_eq!(w.state, ChoiseState::WaitForRespond);
@@ -471,16 +999,7 @@ mod test {
 	#[test]
 	fn test_one_choise() {
 		let vocabulary: Vec<_> = ["abc", "abd"].iter().map(|x| x.to_string()).collect();
-		let candidate_words = vocabulary.clone();
-		let mut w = WordsChooser {
-			words_container: WordsContainer {
-				vocabulary,
-				candidate_words,
-			},
-			state: ChoiseState::ReadyToMakeGuess,
-			word_len: 3,
-			last_word: None,
-		};
+		let mut w = WordsChooser::new(&mut vocabulary.iter(), None).unwrap();
 
 		let attempt1 = w.make_guess().unwrap().to_owned();
 		assert_eq!(w.last_word.as_ref().unwrap(), &attempt1);
@@ -514,16 +1033,7 @@ mod test {
 			.iter()
 			.map(|x| x.to_string())
 			.collect();
-		let candidate_words = vocabulary.clone();
-		let mut w = WordsChooser {
-			words_container: WordsContainer {
-				vocabulary,
-				candidate_words,
-			},
-			state: ChoiseState::ReadyToMakeGuess,
-			word_len: 3,
-			last_word: None,
-		};
+		let mut w = WordsChooser::new(&mut vocabulary.iter(), None).unwrap();
 
 		let attempt1 = w.make_guess().unwrap();
 		// Check for the best options
@@ -540,4 +1050,244 @@ mod test {
 		assert_eq!(w.words_container.candidate_words.len(), 1);
 		assert_eq!(w.make_guess().unwrap(), "bde");
 	}
+
+	// Play until the hidden word is guessed, return the number of attempts
+	fn play(w: &mut WordsChooser, hidden_word: &str) -> usize {
+		for counter in 1.. {
+			let attempt = w.make_guess().unwrap().to_owned();
+			if attempt == hidden_word {
+				return counter;
+			}
+			w.respond_to_guess(&calc_answer_vector(&attempt, hidden_word));
+		}
+		unreachable!()
+	}
+
+	#[test]
+	fn test_word_lengths() {
+		let letters: Vec<_> = "abcdefghij".chars().collect();
+		for len in 4..=8 {
+			// Words with and without repeated letters
+			let words: Vec<String> = (0..20)
+				.map(|i| {
+					(0..len)
+						.map(|j| letters[(i * (j + 1) + j * j) % letters.len()])
+						.collect()
+				})
+				.collect();
+			for hidden_word in words.iter() {
+				let mut w = WordsChooser::new(&mut words.iter(), None).unwrap();
+				assert_eq!(w.word_len, len);
+				assert!(play(&mut w, hidden_word) <= words.len());
+			}
+		}
+	}
+
+	#[test]
+	fn test_invalid_vocabulary() {
+		let words = ["abcd", "abc"];
+		assert!(WordsChooser::new(&mut words.iter(), None).is_err());
+		assert!(WordsChooser::new(&mut words[..1].iter(), Some(5)).is_err());
+		assert!(WordsChooser::new(&mut Vec::<String>::new().iter(), None).is_err());
+
+		let mut w = WordsChooser::new(&mut Vec::<String>::new().iter(), Some(6)).unwrap();
+		assert!(w.make_guess().is_none());
+		assert!(w
+			.with_allowed_guesses(&mut ["abcdef", "abc"].iter())
+			.is_err());
+	}
+
+	#[test]
+	fn test_allowed_guesses() {
+		// Every answer leaves three others after itself, while "cde" separates all of them
+		let answers = ["abc", "abd", "abe", "abf"];
+		let mut w = WordsChooser::new(&mut answers.iter(), None)
+			.unwrap()
+			.with_allowed_guesses(&mut ["cde", "abc", "cde"].iter())
+			.unwrap();
+		assert_eq!(w.words_container.vocabulary.len(), 5);
+		assert_eq!(w.make_guess(), Some("cde"));
+		w.respond_to_guess(&calc_answer_vector("cde", "abe"));
+		assert_eq!(w.words_container.candidate_words, ["abe"]);
+		assert_eq!(w.make_guess(), Some("abe"));
+
+		// The guess that can't be hidden is never a candidate
+		let mut w = WordsChooser::new(&mut answers[..1].iter(), None)
+			.unwrap()
+			.with_allowed_guesses(&mut ["cde"].iter())
+			.unwrap();
+		assert_eq!(w.make_guess(), Some("abc"));
+		w.respond_to_guess(&calc_answer_vector("abc", "cde"));
+		assert!(w.make_guess().is_none());
+	}
+
+	#[test]
+	fn test_weights() {
+		// Both probes separate two answers and leave two others together, the candidates separate less
+		let answers = ["abcd", "abce", "abcf", "abcg"];
+		let choose = |weights: &[(&&str, f64)]| {
+			WordsChooser::new(&mut answers.iter(), None)
+				.unwrap()
+				.with_allowed_guesses(&mut ["fgxy", "dexy"].iter())
+				.unwrap()
+				.with_weights(&mut weights.iter().copied())
+				.unwrap()
+				.make_guess()
+				.unwrap()
+				.to_owned()
+		};
+		assert_eq!(choose(&[]), "fgxy");
+		// "fgxy" leaves the likely words together
+		assert_eq!(choose(&[(&"abcd", 5.0), (&"abce", 5.0)]), "dexy");
+
+		// The most likely of the last candidates is guessed first
+		let mut w = WordsChooser::new(&mut answers[..2].iter(), None)
+			.unwrap()
+			.with_weights(&mut [(&"abce", 3.0)].into_iter())
+			.unwrap();
+		assert_eq!(w.make_guess(), Some("abce"));
+		assert!((w.words_container.weight("abce") - 1.5).abs() < 1e-9);
+
+		let w = WordsChooser::new(&mut answers.iter(), None).unwrap();
+		assert!(w
+			.clone()
+			.with_weights(&mut [(&"dexy", 1.0)].into_iter())
+			.is_err());
+		assert!(w.with_weights(&mut [(&"abcd", 0.0)].into_iter()).is_err());
+
+		// An allowed guess can be in the vocabulary, but it's never hidden
+		let res = WordsChooser::new(&mut answers.iter(), None)
+			.unwrap()
+			.with_allowed_guesses(&mut ["dexy"].iter())
+			.unwrap()
+			.with_weights(&mut [(&"dexy", 1.0)].into_iter());
+		assert_eq!(
+			res.err(),
+			Some("The word \"dexy\" isn't one of the answers".to_owned())
+		);
+	}
+
+	#[test]
+	fn test_lookahead() {
+		// Random words of a realistic list, answers are a part of them
+		let mut state = 12345u64;
+		let mut words = std::collections::BTreeSet::new();
+		while words.len() < 2000 {
+			let word: String = (0..5)
+				.map(|_| {
+					state = state
+						.wrapping_mul(6364136223846793005)
+						.wrapping_add(1442695040888963407);
+					(b'a' + (state >> 33) as u8 % 16) as char
+				})
+				.collect();
+			words.insert(word);
+		}
+		let words: Vec<_> = words.into_iter().collect();
+		let answers: Vec<_> = words.iter().step_by(4).collect();
+		let chooser = WordsChooser::new(&mut answers.iter(), None)
+			.unwrap()
+			.with_allowed_guesses(&mut words.iter())
+			.unwrap()
+			.with_scoring(ScoringStrategy::Lookahead);
+
+		// The lookahead picks one of the best attempts by entropy
+		let first = chooser.suggest().unwrap();
+		let entropy = chooser.clone().with_scoring(ScoringStrategy::Entropy);
+		let list = entropy.words_container.construct_list_of_words();
+		let scores = entropy.scores(&list);
+		let rank = scores
+			.iter()
+			.filter(|x| **x < scores[list.iter().position(|x| *x == first).unwrap()])
+			.count();
+		assert!(rank < LOOKAHEAD_WIDTH);
+
+		for hidden in answers.iter().step_by(100) {
+			let mut w = chooser.clone();
+			let mut guesses = 0;
+			loop {
+				let guess = w.make_guess().unwrap().to_owned();
+				guesses += 1;
+				if &guess == *hidden {
+					break;
+				}
+				assert!(guesses < 8);
+				w.respond_to_guess(&calc_answer_vector(&guess, hidden));
+			}
+		}
+	}
+
+	#[test]
+	fn test_apply_answer() {
+		let answers = ["abc", "abd", "abe", "abf"];
+		let mut w = WordsChooser::new(&mut answers.iter(), None).unwrap();
+		assert_eq!(w.suggest(), w.clone().make_guess().map(str::to_owned));
+		w.make_guess().unwrap();
+		// The attempts give the same answer, but different candidates remain
+		let mut other = w.clone();
+		w.apply_answer("abc", &calc_answer_vector("abc", "abe"))
+			.unwrap();
+		other
+			.apply_answer("abd", &calc_answer_vector("abd", "abe"))
+			.unwrap();
+		assert_eq!(w.words_container.candidate_words, ["abd", "abe", "abf"]);
+		for w in [&mut w, &mut other] {
+			let guess = w.make_guess().unwrap().to_owned();
+			assert!(w.words_container.candidate_words.contains(&guess));
+		}
+
+		assert!(w.apply_answer("abcd", &[CharResult::FullMatch; 4]).is_err());
+		assert!(w.apply_answer("abc", &[CharResult::FullMatch; 2]).is_err());
+		assert_eq!(w.candidates_count(), 3);
+		// The answers may be inconsistent
+		w.apply_answer("xyz", &[CharResult::FullMatch; 3]).unwrap();
+		assert_eq!(w.candidates_count(), 0);
+		assert!(w.suggest().is_none());
+	}
+
+	#[test]
+	fn test_score_candidates() {
+		// See test_smartest_choise: "abc" and "cbg" separate all candidates
+		let vocabulary = ["bde", "fcb", "abc", "cbg"];
+		let mut w = WordsChooser::new(&mut vocabulary.iter(), None)
+			.unwrap()
+			.with_allowed_guesses(&mut ["xyz"].iter())
+			.unwrap();
+		let scores = w.score_candidates();
+		assert_eq!(
+			scores,
+			[
+				("abc".to_owned(), 1.0),
+				("cbg".to_owned(), 1.0),
+				("fcb".to_owned(), 2.0),
+				("bde".to_owned(), 3.0)
+			]
+		);
+		w.apply_answer("xyz", &[CharResult::NotPresented; 3])
+			.unwrap();
+		assert_eq!(w.score_candidates().len(), 4);
+		w.apply_answer("abc", &calc_answer_vector("abc", "cbg"))
+			.unwrap();
+		assert_eq!(w.score_candidates(), [("cbg".to_owned(), 1.0)]);
+	}
+
+	#[test]
+	fn test_hard_mode() {
+		let answers = ["abc", "abd", "abe", "abf", "abg"];
+		let play_after_abc = |hard_mode| {
+			let mut w = WordsChooser::new(&mut answers.iter(), None)
+				.unwrap()
+				.with_allowed_guesses(&mut ["dfg"].iter())
+				.unwrap()
+				.with_hard_mode(hard_mode);
+			w.make_guess().unwrap();
+			w.last_word = Some("abc".to_owned());
+			w.respond_to_guess(&calc_answer_vector("abc", "abe"));
+			w.make_guess().unwrap().to_owned()
+		};
+		// "dfg" separates all candidates, but it doesn't use the known letters
+		assert_eq!(play_after_abc(false), "dfg");
+		let guess = play_after_abc(true);
+		assert!(guess.starts_with("ab"));
+	}
 }